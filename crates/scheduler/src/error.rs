@@ -0,0 +1,142 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::TaskId;
+use crate::time::{
+    CalendarError, CronError, SpanError, TimeError, TimeSpanError, TimestampError, TimeZoneError,
+};
+
+/// Every error of the crate converts into this one, so `?` works across the whole API
+/// ```
+/// use scheduler::{Scheduler, SchedulerError};
+/// use scheduler::time::AsTimeSpan;
+///
+/// fn setup() -> Result<Scheduler, SchedulerError> {
+///     let mut scheduler = Scheduler::with_tz("Europe/Athens")?;
+///     scheduler.every(1.day().at("3:45pm")?).perform(|| println!("hello!"));
+///     scheduler.every("every monday at 10am".parse()?).perform(|| println!("hi!"));
+///     Ok(scheduler)
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchedulerError {
+    /// A time of day, schedule, cron expression, date-time or holiday calendar that's invalid
+    Parse(ParseError),
+    /// A span was given a day, date or window it can't have, e.g. `1.month().on_the(32)`
+    Span(SpanError),
+    /// The timezone is neither a valid UTC offset nor a known IANA name
+    TimeZone(TimeZoneError),
+    /// None of the schedules of the [crate::Scheduler] performs this task
+    UnknownTask(TaskId),
+    /// The task panicked, `reason` is the panic message
+    TaskFailed { task_id: TaskId, reason: String },
+    /// The [crate::Scheduler] was shut down and doesn't run tasks anymore
+    Shutdown,
+    /// The executor doesn't run async tasks, see [crate::executor::Executor::runs_async]
+    AsyncUnsupported,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Time(TimeError),
+    TimeSpan(TimeSpanError),
+    Cron(CronError),
+    Timestamp(TimestampError),
+    Calendar(CalendarError),
+}
+
+impl Display for SchedulerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(err) => write!(f, "{err}"),
+            Self::Span(err) => write!(f, "{err}"),
+            Self::TimeZone(err) => write!(f, "{err}"),
+            Self::UnknownTask(task_id) => write!(f, "unknown task {}", task_id.0),
+            Self::TaskFailed { task_id, reason } => {
+                write!(f, "task {} failed: {reason}", task_id.0)
+            }
+            Self::Shutdown => write!(f, "the scheduler was shut down"),
+            Self::AsyncUnsupported => write!(f, "async tasks need an executor that runs them"),
+        }
+    }
+}
+
+impl Error for SchedulerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Parse(err) => Some(err),
+            Self::Span(err) => Some(err),
+            Self::TimeZone(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Time(err) => write!(f, "{err}"),
+            Self::TimeSpan(err) => write!(f, "{err}"),
+            Self::Cron(err) => write!(f, "{err}"),
+            Self::Timestamp(err) => write!(f, "{err}"),
+            Self::Calendar(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Time(err) => Some(err),
+            Self::TimeSpan(err) => Some(err),
+            Self::Cron(err) => Some(err),
+            Self::Timestamp(err) => Some(err),
+            Self::Calendar(err) => Some(err),
+        }
+    }
+}
+
+impl From<ParseError> for SchedulerError {
+    fn from(err: ParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl From<SpanError> for SchedulerError {
+    fn from(err: SpanError) -> Self {
+        Self::Span(err)
+    }
+}
+
+impl From<TimeZoneError> for SchedulerError {
+    fn from(err: TimeZoneError) -> Self {
+        Self::TimeZone(err)
+    }
+}
+
+/// Implements `From<$err>` for [ParseError] and [SchedulerError]
+macro_rules! parse_error {
+    ($($variant:ident($err:ty)),+ $(,)?) => {
+        $(
+            impl From<$err> for ParseError {
+                fn from(err: $err) -> Self {
+                    Self::$variant(err)
+                }
+            }
+
+            impl From<$err> for SchedulerError {
+                fn from(err: $err) -> Self {
+                    Self::Parse(ParseError::$variant(err))
+                }
+            }
+        )+
+    };
+}
+
+parse_error!(
+    Time(TimeError),
+    TimeSpan(TimeSpanError),
+    Cron(CronError),
+    Timestamp(TimestampError),
+    Calendar(CalendarError),
+);
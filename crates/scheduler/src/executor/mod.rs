@@ -1,21 +1,63 @@
-use crate::Task;
+pub use thread_pool::*;
+#[cfg(feature = "tokio")]
+pub use tokio_executor::*;
+
+use crate::{SchedulerError, Task, TaskId};
 
 pub mod task;
+mod thread_pool;
+#[cfg(feature = "tokio")]
+mod tokio_executor;
+
+/// Runs the tasks that the [crate::Scheduler] hands over once they're due, see
+/// [crate::Scheduler::with_executor]
+pub trait Executor: Send {
+    /// Starts running `task`. Fails when the task can't be run, e.g. after [Executor::shutdown],
+    /// whether the task itself failed is reported by [Executor::completed]
+    fn execute(&mut self, task: Task) -> Result<(), SchedulerError>;
+
+    /// The tasks that finished since the last call, in the order they finished
+    fn completed(&mut self) -> Vec<Completion>;
+
+    /// Waits for the running tasks to finish, no task runs afterwards
+    fn shutdown(&mut self);
+
+    /// Whether the executor runs async tasks, schedules refuse them otherwise, see
+    /// [crate::Schedule::perform_async]
+    fn runs_async(&self) -> bool {
+        false
+    }
+}
 
-pub(crate) struct Executor<'e> {
-    jobs: Vec<&'e Task>,
+/// The outcome of a task that was run by an [Executor]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub task_id: TaskId,
+    pub result: Result<(), SchedulerError>,
 }
 
-impl<'e> Default for Executor<'e> {
-    fn default() -> Self {
-        Self {
-            jobs: Vec::new()
+/// Runs every task on the thread that calls [crate::Scheduler::run_pending], the default
+#[derive(Default)]
+pub struct InlineExecutor {
+    completions: Vec<Completion>,
+    shutdown: bool,
+}
+
+impl Executor for InlineExecutor {
+    fn execute(&mut self, task: Task) -> Result<(), SchedulerError> {
+        if self.shutdown {
+            return Err(SchedulerError::Shutdown);
         }
+        let result = task.run();
+        self.completions.push(Completion { task_id: task.id(), result });
+        Ok(())
     }
-}
 
-impl<'e> Executor<'e> {
-    pub fn new() -> Self {
-        Self::default()
+    fn completed(&mut self) -> Vec<Completion> {
+        std::mem::take(&mut self.completions)
     }
-}
\ No newline at end of file
+
+    fn shutdown(&mut self) {
+        self.shutdown = true;
+    }
+}
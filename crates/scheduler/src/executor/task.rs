@@ -1,11 +1,34 @@
+use std::any::Any;
 use std::fmt::Debug;
+#[cfg(feature = "tokio")]
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use crate::SchedulerError;
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub struct TaskId(pub usize);
 
+/// Clones share the same job, so a task can be performed by several schedules, see
+/// [crate::Schedule::perform_task]
+#[derive(Clone)]
 pub struct Task {
     id: TaskId,
-    job: Option<Box<dyn FnMut() + Send + Sync + 'static>>,
+    job: Option<Job>,
+}
+
+#[cfg(feature = "tokio")]
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+#[derive(Clone)]
+enum Job {
+    Blocking(Arc<Mutex<dyn FnMut() + Send + 'static>>),
+    /// Every run gets a new future, runs of the same task may overlap
+    #[cfg(feature = "tokio")]
+    Async(Arc<dyn Fn() -> BoxFuture + Send + Sync + 'static>),
 }
 
 impl TaskId {
@@ -23,15 +46,87 @@ impl Task {
     where
         F: FnMut() + Send + Sync + 'static,
     {
-        self.job.replace(Box::new(handler));
+        self.job.replace(Job::Blocking(Arc::new(Mutex::new(handler))));
+    }
+
+    /// Sets a job that returns a future, which [crate::executor::TokioExecutor] runs on its
+    /// runtime. Other executors don't run it, see [Task::run]
+    #[cfg(feature = "tokio")]
+    pub fn set_async_handler<F, Fut>(&mut self, handler: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.job.replace(Job::Async(Arc::new(move || Box::pin(handler()) as BoxFuture)));
     }
 
     pub fn id(&self) -> TaskId {
         self.id
     }
 
-    pub(crate) fn run(&self) -> Result<(), ()> {
-        Ok(())
+    /// The same job under another id, see [crate::Schedule::perform_task]
+    pub(crate) fn with_id(&self, id: TaskId) -> Self {
+        Self { id, job: self.job.clone() }
     }
 
+    /// Runs the job, a job that panics fails with [SchedulerError::TaskFailed]. Tasks without a job
+    /// do nothing. Async jobs need a runtime and fail with [SchedulerError::AsyncUnsupported], see
+    /// [crate::executor::TokioExecutor]
+    pub fn run(&self) -> Result<(), SchedulerError> {
+        match self.job {
+            None => Ok(()),
+            Some(Job::Blocking(ref job)) => {
+                // the panic is caught while the lock is held, so the lock is never poisoned by the
+                // job
+                let mut job = job.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                panic::catch_unwind(AssertUnwindSafe(|| (*job)())).map_err(|payload| {
+                    SchedulerError::TaskFailed { task_id: self.id, reason: panic_reason(payload) }
+                })
+            }
+            #[cfg(feature = "tokio")]
+            Some(Job::Async(_)) => Err(SchedulerError::AsyncUnsupported),
+        }
+    }
+
+    /// Whether the job returns a future, see [Task::set_async_handler]
+    pub fn is_async(&self) -> bool {
+        match self.job {
+            #[cfg(feature = "tokio")]
+            Some(Job::Async(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Runs the job on the current runtime, blocking jobs on its blocking threads
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn run_async(self) -> Result<(), SchedulerError> {
+        let task_id = self.id;
+        let joined = match self.job {
+            None => return Ok(()),
+            Some(Job::Async(ref job)) => tokio::spawn(job()).await,
+            // blocking jobs catch their own panics
+            Some(Job::Blocking(_)) => match tokio::task::spawn_blocking(move || self.run()).await {
+                Ok(result) => return result,
+                Err(err) => Err(err),
+            },
+        };
+        joined.map_err(|err| {
+            let reason = match err.try_into_panic() {
+                Ok(payload) => panic_reason(payload),
+                Err(_) => "the task was cancelled".to_string(),
+            };
+            SchedulerError::TaskFailed { task_id, reason }
+        })
+    }
+}
+
+/// The message the job panicked with
+fn panic_reason(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(reason) => *reason,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(reason) => reason.to_string(),
+            Err(_) => "the task panicked".to_string(),
+        },
+    }
 }
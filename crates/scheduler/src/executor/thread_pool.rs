@@ -0,0 +1,149 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::{SchedulerError, Task};
+use crate::executor::{Completion, Executor};
+
+/// Runs tasks on a fixed number of worker threads, so that a slow task doesn't hold up the others
+/// or the [crate::Scheduler]. A task that is due again while it's still running waits for its
+/// previous run to finish
+/// ```
+/// use scheduler::Scheduler;
+/// use scheduler::executor::ThreadPool;
+/// use scheduler::time::AsTimeSpan;
+///
+/// let mut scheduler = Scheduler::new().with_executor(ThreadPool::new(4));
+/// scheduler.every(1.minute()).perform(|| println!("on a worker thread!"));
+///
+/// scheduler.run_pending();
+/// for completion in scheduler.finished() {
+///     if let Err(err) = completion.result {
+///         eprintln!("{err}");
+///     }
+/// }
+/// scheduler.shutdown();
+/// ```
+pub struct ThreadPool {
+    /// `None` once the pool is shut down, which disconnects the workers
+    tasks: Option<Sender<Task>>,
+    completions: Receiver<Completion>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    /// Spawns `workers` threads, at least one
+    pub fn new(workers: usize) -> Self {
+        let (tasks, queue) = mpsc::channel::<Task>();
+        let queue = Arc::new(Mutex::new(queue));
+        let (done, completions) = mpsc::channel();
+
+        let workers = (0..workers.max(1))
+            .map(|idx| {
+                let (queue, done) = (queue.clone(), done.clone());
+                thread::Builder::new()
+                    .name(format!("scheduler-worker-{idx}"))
+                    .spawn(move || Self::work(&queue, &done))
+                    .expect("failed to spawn a worker thread")
+            })
+            .collect();
+
+        Self { tasks: Some(tasks), completions, workers }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Runs the queued tasks until the pool is shut down
+    fn work(queue: &Mutex<Receiver<Task>>, done: &Sender<Completion>) {
+        loop {
+            // the queue is only locked while waiting for the next task, never while running one
+            let next = queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).recv();
+            let Ok(task) = next else {
+                break;
+            };
+            let result = task.run();
+            // nobody is waiting for completions anymore once the pool is dropped
+            let _ = done.send(Completion { task_id: task.id(), result });
+        }
+    }
+}
+
+impl Executor for ThreadPool {
+    fn execute(&mut self, task: Task) -> Result<(), SchedulerError> {
+        let tasks = self.tasks.as_ref().ok_or(SchedulerError::Shutdown)?;
+        tasks.send(task).map_err(|_| SchedulerError::Shutdown)
+    }
+
+    fn completed(&mut self) -> Vec<Completion> {
+        self.completions.try_iter().collect()
+    }
+
+    /// Lets the workers finish the queued tasks and waits for them to exit
+    fn shutdown(&mut self) {
+        self.tasks.take();
+        for worker in self.workers.drain(..) {
+            // the tasks catch their own panics, so the workers never panic
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    use crate::{SchedulerError, Task, TaskId};
+    use crate::executor::{Completion, Executor, ThreadPool};
+
+    fn task<F: FnMut() + Send + Sync + 'static>(id: usize, job: F) -> Task {
+        let mut task = Task::new(TaskId(id));
+        task.set_handler(job);
+        task
+    }
+
+    #[test]
+    fn test_thread_pool() {
+        let mut pool = ThreadPool::new(3);
+        assert_eq!(pool.workers(), 3);
+        assert_eq!(ThreadPool::new(0).workers(), 1);
+
+        // every task waits for the others, so they only finish when they run in parallel
+        let barrier = Arc::new(Barrier::new(3));
+        for id in 0..3 {
+            let barrier = barrier.clone();
+            let name = move || thread::current().name().map(str::to_string);
+            let job = move || {
+                assert!(name().is_some_and(|name| name.starts_with("scheduler-worker-")));
+                barrier.wait();
+            };
+            assert_eq!(pool.execute(task(id, job)), Ok(()));
+        }
+        pool.execute(task(3, || panic!("disk full"))).unwrap();
+
+        pool.shutdown();
+        let mut completed = pool.completed();
+        completed.sort_by_key(|completion| completion.task_id.0);
+        let failed = Completion {
+            task_id: TaskId(3),
+            result: Err(SchedulerError::TaskFailed {
+                task_id: TaskId(3),
+                reason: "disk full".to_string(),
+            }),
+        };
+        assert_eq!(completed.len(), 4);
+        assert!(completed[..3].iter().all(|completion| completion.result.is_ok()));
+        assert_eq!(completed[3], failed);
+
+        assert_eq!(pool.execute(task(4, || {})), Err(SchedulerError::Shutdown));
+        assert!(pool.completed().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::task::JoinHandle;
+
+use crate::{SchedulerError, Task};
+use crate::executor::{Completion, Executor};
+
+/// Runs tasks on a tokio runtime without blocking the thread that calls
+/// [crate::Scheduler::run_pending]. Async tasks run as tokio tasks, see
+/// [crate::Schedule::perform_async], and blocking tasks on the runtime's blocking threads.
+/// Clones share the running tasks, so a clone can [TokioExecutor::wait] for them within the
+/// runtime, see [TokioExecutor::shutdown] for why that's needed on a current-thread runtime
+/// ```
+/// use scheduler::Scheduler;
+/// use scheduler::executor::TokioExecutor;
+/// use scheduler::time::AsTimeSpan;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let executor = TokioExecutor::new();
+///     let mut scheduler = Scheduler::new().with_executor(executor.clone());
+///     scheduler.every(1.minute()).perform_async(|| async { println!("hello!") }).unwrap();
+///
+///     for _ in 0..3 {
+///         scheduler.run_pending();
+///         tokio::task::yield_now().await;
+///     }
+///     executor.wait().await;
+///     scheduler.shutdown();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct TokioExecutor {
+    handle: Handle,
+    done: Sender<Completion>,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    completions: Mutex<Receiver<Completion>>,
+    running: Mutex<Vec<JoinHandle<()>>>,
+    shutdown: AtomicBool,
+}
+
+impl TokioExecutor {
+    /// Runs the tasks on the current runtime, panics outside of a runtime like [tokio::spawn]
+    pub fn new() -> Self {
+        Self::with_handle(Handle::current())
+    }
+
+    /// Runs the tasks on the runtime of `handle`, e.g. when the scheduler has a thread of its own
+    pub fn with_handle(handle: Handle) -> Self {
+        let (done, completions) = mpsc::channel();
+        let shared = Shared {
+            completions: Mutex::new(completions),
+            running: Mutex::new(Vec::new()),
+            shutdown: AtomicBool::new(false),
+        };
+        Self { handle, done, shared: Arc::new(shared) }
+    }
+
+    /// Waits for the running tasks to finish, including the ones that start meanwhile
+    pub async fn wait(&self) {
+        loop {
+            let running = std::mem::take(&mut *lock(&self.shared.running));
+            if running.is_empty() {
+                break;
+            }
+            for task in running {
+                // the tasks report their own failures
+                let _ = task.await;
+            }
+        }
+    }
+}
+
+impl Default for TokioExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Executor for TokioExecutor {
+    fn execute(&mut self, task: Task) -> Result<(), SchedulerError> {
+        if self.shared.shutdown.load(Ordering::Acquire) {
+            return Err(SchedulerError::Shutdown);
+        }
+
+        let done = self.done.clone();
+        let running = self.handle.spawn(async move {
+            let task_id = task.id();
+            let result = task.run_async().await;
+            // nobody is waiting for completions anymore once the executor is dropped
+            let _ = done.send(Completion { task_id, result });
+        });
+
+        let mut tasks = lock(&self.shared.running);
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(running);
+        Ok(())
+    }
+
+    fn completed(&mut self) -> Vec<Completion> {
+        lock(&self.shared.completions).try_iter().collect()
+    }
+
+    /// Waits for the running tasks to finish. Within a multi-threaded runtime the thread is handed
+    /// over to the other tasks while it waits, see [tokio::task::block_in_place]
+    ///
+    /// # Panics
+    ///
+    /// Within a current-thread runtime while tasks are still running, waiting would block the only
+    /// thread that runs them. Await [TokioExecutor::wait] before shutting down there
+    fn shutdown(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Release);
+        let Ok(current) = Handle::try_current() else {
+            return self.handle.block_on(self.wait());
+        };
+        match current.runtime_flavor() {
+            RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| self.handle.block_on(self.wait()))
+            }
+            _ => assert!(
+                lock(&self.shared.running).iter().all(JoinHandle::is_finished),
+                "a current-thread runtime can't wait for its tasks, await TokioExecutor::wait first"
+            ),
+        }
+    }
+
+    fn runs_async(&self) -> bool {
+        true
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // the lock is never held while running a task, so it's never poisoned
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use crate::{Scheduler, SchedulerError, Task, TaskId};
+    use crate::executor::{Completion, Executor, TokioExecutor};
+    use crate::time::{AsTimeSpan, MINUTE_IN_SECS, MockClock};
+    use crate::time::timestamp::Timestamp;
+
+    #[tokio::test]
+    async fn test_tokio_executor() {
+        let clock = MockClock::new(Timestamp::new(1_718_355_600));
+        let mut executor = TokioExecutor::new();
+        let mut scheduler =
+            Scheduler::new().with_clock(clock.clone()).with_executor(executor.clone());
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (sync_runs, async_runs) = (runs.clone(), runs.clone());
+        scheduler.every(1.minute()).perform(move || {
+            sync_runs.fetch_add(1, Ordering::SeqCst);
+        });
+        let counting = scheduler.every(1.minute()).perform_async(move || {
+            let runs = async_runs.clone();
+            async move {
+                tokio::task::yield_now().await;
+                runs.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        assert!(counting.is_ok());
+        let failing = scheduler.every(1.minute()).perform_async(|| async { panic!("disk full") });
+        let failing = failing.unwrap();
+
+        clock.advance(Duration::from_secs(MINUTE_IN_SECS));
+        assert_eq!(scheduler.run_pending().len(), 3);
+        executor.wait().await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        let mut finished = scheduler.finished();
+        finished.sort_by_key(|completion| completion.task_id.0);
+        assert_eq!(finished.len(), 3);
+        assert!(finished[..2].iter().all(|completion| completion.result.is_ok()));
+        let failed = Err(SchedulerError::TaskFailed {
+            task_id: failing,
+            reason: "disk full".to_string(),
+        });
+        assert_eq!(finished[2], Completion { task_id: failing, result: failed });
+
+        scheduler.shutdown();
+        assert_eq!(executor.execute(Task::new(TaskId(9))), Err(SchedulerError::Shutdown));
+        assert!(executor.completed().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_shutdown() {
+        let clock = MockClock::new(Timestamp::new(1_718_355_600));
+        let mut scheduler =
+            Scheduler::new().with_clock(clock.clone()).with_executor(TokioExecutor::new());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        scheduler.every(1.minute()).perform(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        clock.advance(Duration::from_secs(MINUTE_IN_SECS));
+        assert_eq!(scheduler.run_pending().len(), 1);
+        // waits for the task within the runtime
+        scheduler.shutdown();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "await TokioExecutor::wait first")]
+    async fn test_shutdown_current_thread() {
+        let clock = MockClock::new(Timestamp::new(1_718_355_600));
+        let mut scheduler =
+            Scheduler::new().with_clock(clock.clone()).with_executor(TokioExecutor::new());
+        scheduler.every(1.minute()).perform_async(|| async {}).unwrap();
+
+        // the task doesn't start before this one yields
+        clock.advance(Duration::from_secs(MINUTE_IN_SECS));
+        assert_eq!(scheduler.run_pending().len(), 1);
+        scheduler.shutdown();
+    }
+
+    #[test]
+    fn test_async_unsupported() {
+        let mut scheduler = Scheduler::new();
+        let refused = scheduler.every(1.minute()).perform_async(|| async {});
+        assert_eq!(refused, Err(SchedulerError::AsyncUnsupported));
+
+        let mut task = Task::new(TaskId(0));
+        task.set_async_handler(|| async {});
+        assert!(task.is_async());
+        assert_eq!(task.run(), Err(SchedulerError::AsyncUnsupported));
+    }
+}
@@ -1,5 +1,7 @@
+pub use error::*;
 pub use scheduler::*;
 
+mod error;
 mod scheduler;
 pub mod executor;
 pub mod time;
@@ -0,0 +1,272 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error;
+
+use crate::{Cadence, Misfire, Schedule, Scheduler};
+use crate::scheduler::schedule::Spread;
+use crate::time::{Clock, TimeSpan, TimeZone};
+use crate::time::serialize::{check_version, FORMAT_VERSION};
+use crate::time::timestamp::Timestamp;
+
+/// Everything about a [Schedule] but its task, to persist it and restore it after a restart with
+/// [Scheduler::restore]. Serialized as a versioned map, e.g. in JSON
+/// `{"version": 1, "span": {...}, "timezone": "Europe/Athens", "times": 10, "runs": 3, ...}`,
+/// where only the span is required
+/// ```
+/// use scheduler::{Scheduler, ScheduleDefinition};
+/// use scheduler::time::AsTimeSpan;
+/// let mut scheduler = Scheduler::new();
+///
+/// let schedule = scheduler.every(1.day().at("09:00").unwrap()).times(3);
+/// let saved = serde_json::to_string(&schedule.definition()).unwrap();
+///
+/// // after a restart
+/// let definition = serde_json::from_str::<ScheduleDefinition>(&saved).unwrap();
+/// scheduler.restore(definition).perform(|| println!("hello!"));
+/// ```
+#[derive(Clone)]
+pub struct ScheduleDefinition {
+    ts: TimeSpan,
+    /// The timezone of the [Scheduler] it's restored in when `None`
+    tz: Option<Arc<TimeZone>>,
+    until: Option<Timestamp>,
+    times: Option<u32>,
+    runs: u32,
+    spread: Option<Spread>,
+    cadence: Cadence,
+    misfire: Misfire,
+    /// The pending run before it's offset, the first run after the restore when `None`
+    next_run: Option<Timestamp>,
+    anchor: Option<Timestamp>,
+    last_run: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DefinitionRepr {
+    version: u32,
+    span: TimeSpan,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    until: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    times: Option<u32>,
+    #[serde(default)]
+    runs: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spread: Option<SpreadRepr>,
+    #[serde(default)]
+    cadence: Cadence,
+    #[serde(default)]
+    misfire: MisfireRepr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_run: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    anchor: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_run: Option<Timestamp>,
+}
+
+/// Windows are in seconds
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SpreadRepr {
+    Jitter { window: u64 },
+    Hash { window: u64, hash: u64 },
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MisfireRepr {
+    #[default]
+    FireOnce,
+    FireAll,
+    Skip,
+    Grace { seconds: u64 },
+}
+
+impl Schedule {
+    /// The definition of the schedule as it is now, including its runs so far
+    pub fn definition(&self) -> ScheduleDefinition {
+        ScheduleDefinition {
+            ts: self.ts.clone(),
+            tz: Some(self.tz.clone()),
+            until: self.until,
+            times: self.times,
+            runs: self.runs,
+            spread: self.spread,
+            cadence: self.cadence,
+            misfire: self.misfire,
+            next_run: Some(self.scheduled_on()),
+            anchor: Some(self.anchor),
+            last_run: self.last_run,
+        }
+    }
+
+    /// Picks up where the defined schedule left off. A pending run that was due while the schedule
+    /// wasn't running is a misfire, see [Schedule::on_misfire]
+    fn restore(&mut self, definition: ScheduleDefinition) {
+        self.until = definition.until;
+        self.times = definition.times;
+        self.runs = definition.runs;
+        self.cadence = definition.cadence;
+        self.misfire = definition.misfire;
+        self.last_run = definition.last_run;
+        self.spread = definition.spread;
+        let next_run = definition.next_run.unwrap_or(self.scheduled_on());
+        self.anchor = definition.anchor.unwrap_or(next_run);
+        self.set_run_on(next_run);
+    }
+}
+
+impl Scheduler {
+    /// Adds a schedule from its definition, e.g. one that was saved before a restart. The task has
+    /// to be set again with [Schedule::perform], see [ScheduleDefinition]
+    pub fn restore(&mut self, definition: ScheduleDefinition) -> &mut Schedule {
+        let task_id = self.next_task_id();
+        let tz = definition.tz.clone().unwrap_or(self.tz.clone());
+        let clock: Arc<dyn Clock> = self.clock.clone();
+        let mut schedule = Schedule::new(definition.ts.clone(), task_id, tz, clock);
+        schedule.restore(definition);
+        self.add_schedule(schedule)
+    }
+}
+
+impl Serialize for ScheduleDefinition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let spread = self.spread.map(|spread| match spread {
+            Spread::Jitter(window) => SpreadRepr::Jitter { window },
+            Spread::Hash(window, hash) => SpreadRepr::Hash { window, hash },
+        });
+        let misfire = match self.misfire {
+            Misfire::FireOnce => MisfireRepr::FireOnce,
+            Misfire::FireAll => MisfireRepr::FireAll,
+            Misfire::Skip => MisfireRepr::Skip,
+            Misfire::Grace(grace) => MisfireRepr::Grace { seconds: grace.as_secs() },
+        };
+
+        DefinitionRepr {
+            version: FORMAT_VERSION,
+            span: self.ts.clone(),
+            timezone: self.tz.as_ref().map(|tz| tz.name().to_string()),
+            until: self.until,
+            times: self.times,
+            runs: self.runs,
+            spread,
+            cadence: self.cadence,
+            misfire,
+            next_run: self.next_run,
+            anchor: self.anchor,
+            last_run: self.last_run,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScheduleDefinition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = DefinitionRepr::deserialize(deserializer)?;
+        check_version(repr.version)?;
+
+        let tz = match repr.timezone {
+            Some(name) => Some(Arc::new(TimeZone::parse(&name).map_err(D::Error::custom)?)),
+            None => None,
+        };
+        let spread = repr.spread.map(|spread| match spread {
+            SpreadRepr::Jitter { window } => Spread::Jitter(window),
+            SpreadRepr::Hash { window, hash } => Spread::Hash(window, hash),
+        });
+        let misfire = match repr.misfire {
+            MisfireRepr::FireOnce => Misfire::FireOnce,
+            MisfireRepr::FireAll => Misfire::FireAll,
+            MisfireRepr::Skip => Misfire::Skip,
+            MisfireRepr::Grace { seconds } => Misfire::Grace(Duration::from_secs(seconds)),
+        };
+
+        Ok(Self {
+            ts: repr.span,
+            tz,
+            until: repr.until,
+            times: repr.times,
+            runs: repr.runs,
+            spread,
+            cadence: repr.cadence,
+            misfire,
+            next_run: repr.next_run,
+            anchor: repr.anchor,
+            last_run: repr.last_run,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use crate::{Misfire, ScheduleDefinition, Scheduler};
+    use crate::time::{AsTimeSpan, DAY_IN_SECS, HOUR_IN_SECS, MockClock};
+    use crate::time::timestamp::Timestamp;
+
+    #[test]
+    fn test_restore() {
+        // 2024-06-14T09:00:00Z, a Friday
+        let now = Timestamp::new(1_718_355_600);
+        let clock = MockClock::new(now);
+        let mut scheduler = Scheduler::with_tz("UTC+3").unwrap().with_clock(clock.clone());
+        let schedule = scheduler
+            .every(1.day().at("13:00").unwrap())
+            .times(5)
+            .spread_by("report", 30.minute())
+            .unwrap()
+            .on_misfire(Misfire::FireAll);
+        schedule.perform(|| {});
+        let (run_on, offset) = (schedule.run_on, schedule.offset);
+        // the first run is on Saturday
+        clock.advance(Duration::from_secs(DAY_IN_SECS + 2 * HOUR_IN_SECS));
+        assert_eq!(scheduler.run_pending().len(), 1);
+
+        let saved = serde_json::to_string(&scheduler.schedules[0].definition()).unwrap();
+        let definition = serde_json::from_str::<ScheduleDefinition>(&saved).unwrap();
+        assert_eq!(serde_json::to_string(&definition).unwrap(), saved);
+
+        // down for two days, the runs of Sunday and Monday were missed
+        clock.advance(Duration::from_secs(2 * DAY_IN_SECS + HOUR_IN_SECS));
+        let mut restarted = Scheduler::new().with_clock(clock.clone());
+        let restored = restarted.restore(definition);
+        assert_eq!(restored.tz.name(), "UTC+3");
+        assert_eq!(restored.runs(), 1);
+        assert_eq!(restored.offset, offset);
+        assert_eq!(restored.run_on, run_on + DAY_IN_SECS);
+        assert_eq!(restored.anchor(), run_on - Duration::from_secs(offset));
+        assert_eq!(restored.to_string(), "every day at 13:00, 5 times");
+
+        restored.perform(|| {});
+        assert_eq!(restarted.run_pending().len(), 2);
+        assert_eq!(restarted.schedules[0].runs(), 3);
+    }
+
+    #[test]
+    fn test_minimal() {
+        let now = Timestamp::new(1_718_355_600);
+        let mut scheduler = Scheduler::new().with_clock(MockClock::new(now));
+        let definition = json!({
+            "version": 1,
+            "span": {"version": 1, "every": {"type": "duration", "seconds": 3600}},
+        });
+        let definition = serde_json::from_value::<ScheduleDefinition>(definition).unwrap();
+        let schedule = scheduler.restore(definition);
+        assert_eq!(schedule.run_on, now + HOUR_IN_SECS);
+        assert_eq!(schedule.runs(), 0);
+
+        let invalid = json!({
+            "version": 1,
+            "span": {"version": 1, "every": {"type": "duration", "seconds": 3600}},
+            "timezone": "Mars/Olympus_Mons",
+        });
+        assert!(serde_json::from_value::<ScheduleDefinition>(invalid).is_err());
+    }
+}
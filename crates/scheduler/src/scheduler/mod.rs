@@ -1,18 +1,41 @@
 use std::ops::Deref;
+use std::sync::Arc;
 
+#[cfg(feature = "serde")]
+pub use definition::*;
 pub use macros::*;
 pub use schedule::*;
 
 pub use crate::executor::task::*;
-use crate::time::TimeSpan;
+use crate::SchedulerError;
+use crate::executor::{Completion, Executor, InlineExecutor};
+use crate::time::{Clock, SystemClock, TimeSpan, TimeZone};
+use crate::time::timestamp::Timestamp;
 
+#[cfg(feature = "serde")]
+mod definition;
 mod schedule;
 #[macro_use]
 mod macros;
 
+/// The most completions [Scheduler::finished] keeps until they're read
+pub const MAX_FINISHED: usize = 1024;
+
 pub struct Scheduler {
     schedules: Vec<Schedule>,
+    /// Schedules that reached their end date or run count, in the order they completed
+    completed: Vec<Schedule>,
     pk: usize,
+    /// Every time of day of the schedules is a wall-clock time in this timezone
+    tz: Arc<TimeZone>,
+    /// Every schedule reads the current time from this clock, see [Scheduler::with_clock]
+    clock: Arc<dyn Clock>,
+    /// Runs the due tasks, see [Scheduler::with_executor]
+    executor: Box<dyn Executor>,
+    /// The completions reported by the executor that weren't read yet, see [Scheduler::finished]
+    finished: Vec<Completion>,
+    /// No task runs after [Scheduler::shutdown]
+    shutdown: bool,
     // _marker: &'s PhantomData<T>,
 }
 
@@ -20,7 +43,13 @@ impl Default for Scheduler {
     fn default() -> Self {
         Self {
             schedules: Vec::new(),
+            completed: Vec::new(),
             pk: 0,
+            tz: Arc::new(TimeZone::utc()),
+            clock: Arc::new(SystemClock),
+            executor: Box::new(InlineExecutor::default()),
+            finished: Vec::new(),
+            shutdown: false,
             // _marker: &PhantomData,
         }
     }
@@ -31,34 +60,206 @@ impl Scheduler {
         Self::default()
     }
 
-    // TODO: the with_tz method needs to parse the _tz &str to a valid timezone. If the timezone is not valid then return an error.
-    // Need to check if this approach should change from a &str to a timezone enum for more predictable results
-    pub fn with_tz(_tz: &str) -> Result<Self, ()> {
-        unimplemented!()
+    /// Accepts fixed offsets ("UTC+3") and IANA timezone names ("Europe/Athens")
+    pub fn with_tz(tz: &str) -> Result<Self, SchedulerError> {
+        Ok(Self {
+            tz: Arc::new(TimeZone::parse(tz)?),
+            ..Self::default()
+        })
+    }
+
+    pub fn timezone(&self) -> &TimeZone {
+        &self.tz
+    }
+
+    /// Replaces the [SystemClock], e.g. with a [crate::time::MockClock] in tests
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        for schedule in self.schedules.iter_mut() {
+            schedule.clock = self.clock.clone();
+        }
+        self
+    }
+
+    /// Replaces the [InlineExecutor], e.g. with a [crate::executor::ThreadPool] to run the tasks on
+    /// worker threads. Async tasks that were added before fail to run if the executor doesn't run
+    /// them, see [Schedule::perform_async]
+    pub fn with_executor<E: Executor + 'static>(mut self, executor: E) -> Self {
+        self.executor = Box::new(executor);
+        for schedule in self.schedules.iter_mut() {
+            schedule.runs_async = self.executor.runs_async();
+        }
+        self
+    }
+
+    /// The current time according to the clock of the scheduler
+    pub fn now(&self) -> Timestamp {
+        self.clock.now()
     }
 
     pub fn every(&mut self, ts: TimeSpan) -> &mut Schedule {
         let task_id = self.next_task_id();
-        let schedule = Schedule::new(ts, task_id);
-        self.add_schedule(task_id, schedule)
+        let schedule = Schedule::new(ts, task_id, self.tz.clone(), self.clock.clone());
+        self.add_schedule(schedule)
+    }
+
+    /// The schedules that haven't completed yet, see [Scheduler::completed] for the others
+    pub fn schedules(&self) -> impl Iterator<Item = &Schedule> + '_ {
+        self.schedules.iter()
     }
 
     fn stop_schedule(&self) -> Option<Schedule> {
         todo!()
     }
 
-    /// Refreshes the schedules to be up-to-date, checks if the `run_on` [Timestamp]s are expired and updates them if necessary. It then sorts
-    /// the `schedules` member in `Scheduler` by ascending order of the `run_on` member
-    pub fn refresh(&mut self) {
+    /// Hands the task of every schedule whose `run_on` [Timestamp] has expired to the executor and
+    /// returns the ids of the tasks it accepted. Every run counts towards [Schedule::times],
+    /// whether the task failed or not is reported by [Scheduler::finished].
+    /// Missed runs are handled by the [crate::Misfire] policy of each schedule. Schedules that are
+    /// done afterwards are retired, see [Scheduler::completed]
+    pub fn run_pending(&mut self) -> Vec<TaskId> {
+        let mut ran = Vec::new();
+        if self.shutdown {
+            return ran;
+        }
+        self.collect_finished();
+        for schedule in self.schedules.iter_mut() {
+            let running = schedule.awaits_completion() && schedule.in_flight > 0;
+            if running || !schedule.has_expired() || schedule.is_completed() {
+                continue;
+            }
+            for _ in 0..schedule.due_runs() {
+                if schedule.is_completed() {
+                    break;
+                }
+                if self.executor.execute(schedule.task.clone()).is_ok() {
+                    ran.push(schedule.task_id());
+                    schedule.in_flight += schedule.awaits_completion() as u32;
+                }
+                schedule.runs += 1;
+                schedule.last_run = Some(schedule.clock.now());
+            }
+            if schedule.in_flight == 0 && !schedule.is_completed() {
+                schedule.next_run_on();
+            }
+        }
+        // the default executor has finished the tasks by now
+        self.collect_finished();
+        self.refresh();
+        ran
+    }
+
+    /// Reads the completions from the executor and schedules the next run of the fixed delay
+    /// schedules whose runs have all finished
+    fn collect_finished(&mut self) {
+        let completions = self.executor.completed();
+        for completion in completions.iter() {
+            let Some(schedule) = self
+                .schedules
+                .iter_mut()
+                .find(|schedule| schedule.task_id() == completion.task_id)
+            else {
+                continue;
+            };
+            if schedule.in_flight > 0 {
+                schedule.in_flight -= 1;
+                if schedule.in_flight == 0 && !schedule.is_completed() {
+                    schedule.next_run_on();
+                }
+            }
+        }
+
+        self.finished.extend(completions);
+        // nobody reads them, the latest ones are kept
+        let excess = self.finished.len().saturating_sub(MAX_FINISHED);
+        self.finished.drain(..excess);
+    }
+
+    /// Runs a task right away, outside of its schedules. Its runs aren't counted towards
+    /// [Schedule::times]
+    pub fn run_task(&self, task_id: TaskId) -> Result<(), SchedulerError> {
+        if self.shutdown {
+            return Err(SchedulerError::Shutdown);
+        }
+        self.task(task_id)?.run()
+    }
+
+    /// The task with `task_id`, e.g. to perform it in another schedule, see [Schedule::perform_task]
+    pub fn task(&self, task_id: TaskId) -> Result<Task, SchedulerError> {
         self.schedules
-            .iter_mut()
-            .filter_map(|sc| sc.has_expired().then(|| sc.next_run_on()));
+            .iter()
+            .chain(self.completed.iter())
+            .find(|schedule| schedule.task_id() == task_id)
+            .map(|schedule| schedule.task.clone())
+            .ok_or(SchedulerError::UnknownTask(task_id))
+    }
+
+    /// The tasks the executor finished running since the last call, in the order they finished.
+    /// Only the latest [MAX_FINISHED] are kept until they're read, so this should be called
+    /// regularly, e.g. after every [Scheduler::run_pending]
+    pub fn finished(&mut self) -> Vec<Completion> {
+        self.collect_finished();
+        self.refresh();
+        std::mem::take(&mut self.finished)
+    }
+
+    /// Stops running tasks and waits for the executor to finish the ones that are running.
+    /// [Scheduler::run_pending] runs nothing and [Scheduler::run_task] fails with
+    /// [SchedulerError::Shutdown] afterwards
+    pub fn shutdown(&mut self) {
+        self.shutdown = true;
+        self.executor.shutdown();
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown
+    }
+
+    /// Every run from `from` until `to` (excluded) across all the schedules, in chronological order.
+    /// Runs are previewed as described in [Schedule::upcoming]
+    pub fn agenda(&self, from: Timestamp, to: Timestamp) -> Vec<(TaskId, Timestamp)> {
+        let mut agenda = self
+            .schedules
+            .iter()
+            .flat_map(|schedule| {
+                let task_id = schedule.task_id();
+                schedule
+                    .upcoming()
+                    .skip_while(move |run_on| *run_on < from)
+                    .take_while(move |run_on| *run_on < to)
+                    .map(move |run_on| (task_id, run_on))
+            })
+            .collect::<Vec<_>>();
+        agenda.sort_by_key(|(_, run_on)| *run_on);
+        agenda
+    }
+
+    /// The ids of the tasks whose schedules have completed, in the order they completed
+    pub fn completed(&self) -> impl Iterator<Item = TaskId> + '_ {
+        self.completed.iter().map(Schedule::task_id)
+    }
+
+    /// Retires the completed schedules and sorts the `schedules` member in `Scheduler` by ascending
+    /// order of the `run_on` member. Expired runs are left for [Scheduler::run_pending] to handle
+    /// according to their [crate::Misfire] policy
+    pub fn refresh(&mut self) {
+        self.retire_completed();
         self.schedules.sort_by(|ref sc1, ref sc2| sc1.run_on.cmp(&sc2.run_on));
     }
 
-    fn add_schedule(&mut self, task_id: TaskId, schedule: Schedule) -> &mut Schedule {
-        self.schedules.insert(task_id.0, schedule);
-        self.schedules.get_mut(task_id.0).unwrap() // we get schedule here because then the schedules may become unordered due to the .refresh() call
+    fn retire_completed(&mut self) {
+        let (completed, schedules): (Vec<_>, Vec<_>) = std::mem::take(&mut self.schedules)
+            .into_iter()
+            .partition(Schedule::is_completed);
+        self.schedules = schedules;
+        self.completed.extend(completed);
+    }
+
+    fn add_schedule(&mut self, mut schedule: Schedule) -> &mut Schedule {
+        schedule.runs_async = self.executor.runs_async();
+        // schedules are reordered and retired by .refresh(), so the task id is no index into them
+        self.schedules.push(schedule);
+        self.schedules.last_mut().unwrap()
     }
 
     fn next_task_id(&mut self) -> TaskId {
@@ -103,3 +304,214 @@ impl Scheduler {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use std::sync::{Arc, mpsc, Mutex};
+    use std::thread;
+
+    use crate::{Scheduler, SchedulerError, TaskId};
+    use crate::executor::{Completion, ThreadPool};
+    use crate::time::{
+        AsTimeSpan, HOUR_IN_SECS, MINUTE_IN_SECS, MockClock, SpanError, TimeZoneError,
+    };
+    use crate::time::timestamp::Timestamp;
+
+    // 2024-06-14T09:00:00Z
+    const NOW: i64 = 1_718_355_600;
+
+    #[test]
+    fn test_times() {
+        let clock = MockClock::new(Timestamp::new(NOW));
+        let mut scheduler = Scheduler::new().with_clock(clock.clone());
+        let id = scheduler.every(1.hour()).times(2).perform(|| {});
+        let forever = scheduler.every(1.hour()).perform(|| {});
+
+        assert!(scheduler.run_pending().is_empty());
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        assert_eq!(scheduler.run_pending(), vec![id, forever]);
+        assert_eq!(scheduler.completed().count(), 0);
+
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        assert_eq!(scheduler.run_pending(), vec![id, forever]);
+        assert_eq!(scheduler.completed().collect::<Vec<_>>(), vec![id]);
+        assert_eq!(scheduler.schedules.len(), 1);
+
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        assert_eq!(scheduler.run_pending(), vec![forever]);
+    }
+
+    #[test]
+    fn test_starting_and_until() {
+        let now = Timestamp::new(NOW);
+        let mut scheduler = Scheduler::new().with_clock(MockClock::new(now));
+
+        let starting = now + 5 * HOUR_IN_SECS;
+        scheduler.every(1.hour()).starting(starting);
+        assert_eq!(scheduler.schedules[0].run_on.as_timestamp(), starting.as_timestamp());
+
+        // the next run already falls after the end date
+        let id = scheduler
+            .every(1.hour())
+            .until(now + 30 * MINUTE_IN_SECS)
+            .perform(|| {});
+        scheduler.refresh();
+        assert_eq!(scheduler.completed().collect::<Vec<_>>(), vec![id]);
+        assert_eq!(scheduler.schedules.len(), 1);
+    }
+
+    #[test]
+    fn test_agenda() {
+        let now = Timestamp::new(NOW);
+        let mut scheduler = Scheduler::new().with_clock(MockClock::new(now));
+        let hourly = scheduler.every(1.hour()).perform(|| {});
+        let half_hourly = scheduler.every(30.minute()).times(2).perform(|| {});
+
+        let agenda = scheduler
+            .agenda(now + 45 * MINUTE_IN_SECS, now + 3 * HOUR_IN_SECS)
+            .into_iter()
+            .map(|(task_id, run_on)| (task_id, (run_on.as_timestamp() - NOW) / 60))
+            .collect::<Vec<_>>();
+        assert_eq!(agenda, vec![(hourly, 60), (half_hourly, 60), (hourly, 120)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Scheduler::with_tz("UTC+19").err(),
+            Some(SchedulerError::TimeZone(TimeZoneError::InvalidOffset("UTC+19".to_string())))
+        );
+
+        let clock = MockClock::new(Timestamp::new(NOW));
+        let mut scheduler = Scheduler::new().with_clock(clock.clone());
+        let count = Arc::new(Mutex::new(0));
+        let counter = count.clone();
+        let id = scheduler.every(1.hour()).perform(move || *counter.lock().unwrap() += 1);
+        let failing = scheduler.every(1.hour()).perform(|| panic!("disk full"));
+
+        assert_eq!(scheduler.run_task(id), Ok(()));
+        assert_eq!(
+            scheduler.run_task(failing),
+            Err(SchedulerError::TaskFailed { task_id: failing, reason: "disk full".to_string() })
+        );
+        assert_eq!(scheduler.run_task(TaskId(7)), Err(SchedulerError::UnknownTask(TaskId(7))));
+        let span = 1.month().on_the(32).map_err(SchedulerError::from);
+        assert_eq!(span.err(), Some(SchedulerError::Span(SpanError::MonthDay(32))));
+
+        // the second schedule shares the job of the first one under an id of its own
+        let task = scheduler.task(id).unwrap();
+        let shared = scheduler.every(30.minute()).times(1).perform_task(task);
+        assert_ne!(shared, id);
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        let mut ran = scheduler.run_pending();
+        ran.sort_by_key(|id| id.0);
+        assert_eq!(ran, vec![id, failing, shared]);
+        assert_eq!(*count.lock().unwrap(), 3);
+        assert_eq!(scheduler.completed().collect::<Vec<_>>(), vec![shared]);
+        assert_eq!(scheduler.task(shared).map(|task| task.id()), Ok(shared));
+        let agenda = scheduler.agenda(scheduler.now(), scheduler.now() + HOUR_IN_SECS + 1);
+        assert!(agenda.iter().all(|(task_id, _)| *task_id != shared));
+
+        scheduler.shutdown();
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        assert!(scheduler.run_pending().is_empty());
+        assert_eq!(scheduler.run_task(id), Err(SchedulerError::Shutdown));
+        assert_eq!(*count.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_executor() {
+        let clock = MockClock::new(Timestamp::new(NOW));
+        let mut scheduler =
+            Scheduler::new().with_clock(clock.clone()).with_executor(ThreadPool::new(2));
+        let count = Arc::new(Mutex::new(0));
+        let counter = count.clone();
+        let id = scheduler.every(1.hour()).perform(move || *counter.lock().unwrap() += 1);
+        let failing = scheduler.every(1.hour()).times(1).perform(|| panic!("disk full"));
+
+        // failing tasks are handed over all the same, their outcome is reported later
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        let mut ran = scheduler.run_pending();
+        ran.sort_by_key(|id| id.0);
+        assert_eq!(ran, vec![id, failing]);
+        assert_eq!(scheduler.completed().collect::<Vec<_>>(), vec![failing]);
+
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        assert_eq!(scheduler.run_pending(), vec![id]);
+
+        // waits for the workers to finish
+        scheduler.shutdown();
+        assert_eq!(*count.lock().unwrap(), 2);
+        let mut finished = scheduler.finished();
+        finished.sort_by_key(|completion| completion.task_id.0);
+        let results = finished
+            .into_iter()
+            .map(|completion| (completion.task_id, completion.result))
+            .collect::<Vec<_>>();
+        let reason = "disk full".to_string();
+        let failed = Err(SchedulerError::TaskFailed { task_id: failing, reason });
+        assert_eq!(results, vec![(id, Ok(())), (id, Ok(())), (failing, failed)]);
+
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        assert!(scheduler.run_pending().is_empty());
+        assert!(scheduler.finished().is_empty());
+    }
+
+    #[test]
+    fn test_inline_executor() {
+        let clock = MockClock::new(Timestamp::new(NOW));
+        let mut scheduler = Scheduler::new().with_clock(clock.clone());
+        let id = scheduler.every(1.hour()).perform(|| {});
+        let failing = scheduler.every(1.hour()).times(1).perform(|| panic!("disk full"));
+
+        // a failed run counts all the same, like with any other executor
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        assert_eq!(scheduler.run_pending(), vec![id, failing]);
+        assert_eq!(scheduler.completed().collect::<Vec<_>>(), vec![failing]);
+        let reason = "disk full".to_string();
+        let failed = Err(SchedulerError::TaskFailed { task_id: failing, reason });
+        let expected = vec![
+            Completion { task_id: id, result: Ok(()) },
+            Completion { task_id: failing, result: failed },
+        ];
+        assert_eq!(scheduler.finished(), expected);
+        assert!(scheduler.finished().is_empty());
+    }
+
+    #[test]
+    fn test_fixed_delay() {
+        let clock = MockClock::new(Timestamp::new(NOW));
+        let mut scheduler =
+            Scheduler::new().with_clock(clock.clone()).with_executor(ThreadPool::new(2));
+        let (done, running) = mpsc::channel::<()>();
+        let running = Mutex::new(running);
+        let id = scheduler
+            .every(1.hour())
+            .fixed_delay()
+            .perform(move || running.lock().unwrap().recv().unwrap());
+
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        assert_eq!(scheduler.run_pending(), vec![id]);
+        // still running hours later, so it doesn't run again
+        clock.advance(Duration::from_secs(3 * HOUR_IN_SECS));
+        assert!(scheduler.run_pending().is_empty());
+
+        done.send(()).unwrap();
+        let finished = loop {
+            match scheduler.finished() {
+                finished if finished.is_empty() => thread::sleep(Duration::from_millis(1)),
+                finished => break finished,
+            }
+        };
+        assert_eq!(finished, vec![Completion { task_id: id, result: Ok(()) }]);
+        // an hour after it finished
+        assert_eq!(scheduler.schedules[0].run_on, Timestamp::new(NOW) + 5 * HOUR_IN_SECS);
+
+        clock.advance(Duration::from_secs(HOUR_IN_SECS));
+        assert_eq!(scheduler.run_pending(), vec![id]);
+        done.send(()).unwrap();
+        scheduler.shutdown();
+    }
+}
@@ -1,31 +1,137 @@
-use crate::{Task, TaskId};
-use crate::time::TimeSpan;
+use std::collections::hash_map::RandomState;
+use std::fmt::{Display, Formatter};
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{SchedulerError, Task, TaskId};
+use crate::time::{Clock, Exclusion, SpanError, TimeSpan, TimeZone};
 use crate::time::timestamp::Timestamp;
 
 pub struct Schedule {
     pub(crate) run_on: Timestamp,
     pub(crate) task: Task,
     pub(crate) ts: TimeSpan,
+    pub(crate) tz: Arc<TimeZone>,
+    pub(crate) clock: Arc<dyn Clock>,
+    /// No run happens after this instant
+    pub(crate) until: Option<Timestamp>,
+    /// The schedule completes after this many runs
+    pub(crate) times: Option<u32>,
+    pub(crate) runs: u32,
+    pub(crate) spread: Option<Spread>,
+    /// The seconds `run_on` is offset by from the run computed by the [TimeSpan]
+    pub(crate) offset: u64,
+    /// The first run computed by the [TimeSpan], fixed rate durations run in multiples of the
+    /// interval from it
+    pub(crate) anchor: Timestamp,
+    pub(crate) cadence: Cadence,
+    pub(crate) misfire: Misfire,
+    /// When the task last ran, according to the clock of the scheduler
+    pub(crate) last_run: Option<Timestamp>,
+    /// The runs handed to the executor that haven't finished yet, see [Cadence::FixedDelay]
+    pub(crate) in_flight: u32,
+    /// Whether the executor of the scheduler runs async tasks, see [Schedule::perform_async]
+    pub(crate) runs_async: bool,
+}
+
+/// Runs that are due for longer than this are misfires, e.g. because the scheduler was down.
+/// [Misfire::Skip] always skips after this minute, see [Misfire::Grace] for other thresholds
+pub const MISFIRE_THRESHOLD: Duration = Duration::from_secs(60);
+
+/// The most runs [Misfire::FireAll] catches up on at once, e.g. for a `1.second()` span that was
+/// down over a weekend. The rest of the missed runs are dropped
+pub const MAX_MISFIRED_RUNS: usize = 100;
+
+/// What happens to the runs that were missed, e.g. while the scheduler was down, see
+/// [Schedule::on_misfire]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Misfire {
+    /// Runs once right away no matter how many runs were missed
+    #[default]
+    FireOnce,
+    /// Runs once for every run that was missed since the last run, at most [MAX_MISFIRED_RUNS]
+    /// times
+    FireAll,
+    /// Skips the runs that are late by more than [MISFIRE_THRESHOLD], a fixed minute, and waits for
+    /// the next one. [Misfire::Grace] skips them after a threshold of your choosing instead
+    Skip,
+    /// Runs once right away if the earliest missed run is late by no more than the grace period,
+    /// skips the missed runs otherwise
+    Grace(Duration),
+}
+
+/// How the next run of a plain duration, e.g. `1.hour()`, is computed after a run. Every other span
+/// is pinned to the calendar and runs at the same times either way
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Cadence {
+    /// In multiples of the interval from the [Schedule::anchor], so late runs never shift the ones
+    /// after them
+    #[default]
+    FixedRate,
+    /// The interval after the previous run has finished, i.e. once the [crate::Scheduler] learns
+    /// that it finished from the executor. It doesn't run again while the previous run is running
+    FixedDelay,
+}
+
+/// Offsets the runs of a [Schedule] within a window of seconds
+#[derive(Clone, Copy)]
+pub(crate) enum Spread {
+    /// A different random offset for every run
+    Jitter(u64),
+    /// The same offset for every run, derived from the hash of the task
+    Hash(u64, u64),
+}
+
+/// The upcoming runs of a [Schedule] in chronological order, see [Schedule::upcoming]
+pub struct Upcoming {
+    ts: TimeSpan,
+    tz: Arc<TimeZone>,
+    spread: Option<Spread>,
+    /// The next run along with the seconds it's offset by
+    next: Option<(Timestamp, u64)>,
+    until: Option<Timestamp>,
+    remaining: Option<u32>,
 }
 
 impl Schedule {
-    pub fn new(mut ts: TimeSpan, task_id: TaskId) -> Self {
-        let run_on = ts.next_run_on();
+    pub fn new(
+        mut ts: TimeSpan,
+        task_id: TaskId,
+        tz: Arc<TimeZone>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let run_on = ts.next_run_on_after(&clock.now(), &tz);
         Self {
-            run_on: run_on,
+            run_on,
             task: Task::new(task_id),
             ts,
+            tz,
+            clock,
+            until: None,
+            times: None,
+            runs: 0,
+            spread: None,
+            offset: 0,
+            anchor: run_on,
+            cadence: Cadence::default(),
+            misfire: Misfire::default(),
+            last_run: None,
+            in_flight: 0,
+            runs_async: false,
         }
     }
 
-    pub fn run_when_ready(&self) -> Result<(), ()> {
-        println!("Running {}...", self.task.id().0);
-        Ok(())
+    /// Runs the task of the schedule, see [Task::run]
+    pub fn run_when_ready(&self) -> Result<(), SchedulerError> {
+        self.task.run()
     }
 
     /// Returns `true` if `has_expired` is true, else returns `false`.
     pub fn has_expired(&mut self) -> bool {
-        self.run_on <= Timestamp::now()
+        self.run_on <= self.clock.now()
     }
 
     pub fn perform<F>(&mut self, task: F) -> TaskId
@@ -37,8 +143,54 @@ impl Schedule {
         self.task.id()
     }
 
-    pub fn perform_task(&self, _p0: ()) -> Result<(), ()> {
-        todo!()
+    /// Performs an async task. Fails with [SchedulerError::AsyncUnsupported] unless the scheduler
+    /// has an executor that runs async tasks, see [crate::executor::TokioExecutor]
+    /// ```
+    /// use scheduler::{Scheduler, SchedulerError};
+    /// use scheduler::executor::TokioExecutor;
+    /// use scheduler::time::AsTimeSpan;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), SchedulerError> {
+    ///     let mut scheduler = Scheduler::new().with_executor(TokioExecutor::new());
+    ///     scheduler.every(1.minute()).perform_async(|| async { println!("on the runtime!") })?;
+    ///     scheduler.run_pending();
+    ///
+    ///     let mut blocking = Scheduler::new();
+    ///     let refused = blocking.every(1.minute()).perform_async(|| async {});
+    ///     assert_eq!(refused, Err(SchedulerError::AsyncUnsupported));
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn perform_async<F, Fut>(&mut self, task: F) -> Result<TaskId, SchedulerError>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        if !self.runs_async {
+            return Err(SchedulerError::AsyncUnsupported);
+        }
+        self.task.set_async_handler(task);
+        Ok(self.task.id())
+    }
+
+    /// Performs the job of a task that another schedule already performs, see
+    /// [crate::Scheduler::task]. The schedule keeps its own task id, so the runs of either schedule
+    /// can be told apart
+    /// ```
+    /// use scheduler::{Scheduler, SchedulerError};
+    /// use scheduler::time::{AsTimeSpan, WeekDay::Monday};
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// let id = scheduler.every(1.day().at("09:00")?).perform(|| println!("backup!"));
+    /// let task = scheduler.task(id)?;
+    /// scheduler.every(Monday.at("18:00")?).perform_task(task);
+    /// # Ok::<(), SchedulerError>(())
+    /// ```
+    pub fn perform_task(&mut self, task: Task) -> TaskId {
+        self.task = task.with_id(self.task.id());
+        self.task.id()
     }
 
     pub fn start_now(&mut self) -> &mut Self {
@@ -46,9 +198,493 @@ impl Schedule {
         todo!()
     }
 
+    /// Skips the runs that fall on excluded weekdays, dates or times of day, see [TimeSpan::except]
+    pub fn except<E: Into<Exclusion>>(&mut self, exclusion: E) -> &mut Self {
+        self.ts.exceptions.push(exclusion.into());
+        let next = self.ts.skip_excluded(self.scheduled_on(), &self.tz);
+        self.set_run_on(next);
+        self
+    }
+
+    /// Delays every run by a random amount of time within `window`, e.g. `.with_jitter(5.minute())`
+    /// Fails for windows that aren't plain durations, e.g. `1.month()`
+    pub fn with_jitter(&mut self, window: TimeSpan) -> Result<&mut Self, SpanError> {
+        Ok(self.set_spread(Spread::Jitter(window.duration_secs()?)))
+    }
+
+    /// Delays every run by the same amount of time within `window`, derived from the task id. Tasks that
+    /// are registered in the same order get the same offsets after a restart
+    pub fn spread(&mut self, window: TimeSpan) -> Result<&mut Self, SpanError> {
+        let hash = fnv1a(&(self.task.id().0 as u64).to_le_bytes());
+        Ok(self.set_spread(Spread::Hash(window.duration_secs()?, hash)))
+    }
+
+    /// Same as [Schedule::spread] but derives the offset from `name`, so it stays the same no matter
+    /// the order the tasks are registered in
+    pub fn spread_by(&mut self, name: &str, window: TimeSpan) -> Result<&mut Self, SpanError> {
+        Ok(self.set_spread(Spread::Hash(window.duration_secs()?, fnv1a(name.as_bytes()))))
+    }
+
+    /// Delays the first run to the first occurrence at or after `ts`, which becomes the anchor
+    pub fn starting(&mut self, ts: Timestamp) -> &mut Self {
+        if self.scheduled_on() < ts {
+            let next = self.ts.first_run_from(&ts, &self.tz);
+            self.anchor = next;
+            self.set_run_on(next);
+        }
+        self
+    }
+
+    /// Runs every interval from the [Schedule::anchor] no matter how late the previous runs were,
+    /// the default
+    pub fn fixed_rate(&mut self) -> &mut Self {
+        self.cadence = Cadence::FixedRate;
+        self
+    }
+
+    /// Runs the interval after the previous run has finished, e.g. to space out long jobs
+    /// ```
+    /// use scheduler::Scheduler;
+    /// use scheduler::time::AsTimeSpan;
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// scheduler
+    ///     .every(10.minute())
+    ///     .fixed_delay()
+    ///     .perform(|| println!("sync that takes a few minutes!"));
+    /// ```
+    pub fn fixed_delay(&mut self) -> &mut Self {
+        self.cadence = Cadence::FixedDelay;
+        self
+    }
+
+    /// Sets what happens to missed runs
+    /// ```
+    /// use std::time::Duration;
+    /// use scheduler::{Misfire, Scheduler};
+    /// use scheduler::time::AsTimeSpan;
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// scheduler
+    ///     .every(1.day().at("02:00").unwrap())
+    ///     .on_misfire(Misfire::Grace(Duration::from_secs(3 * 3600)))
+    ///     .perform(|| println!("nightly backup, unless it's already morning!"));
+    /// ```
+    pub fn on_misfire(&mut self, misfire: Misfire) -> &mut Self {
+        self.misfire = misfire;
+        self
+    }
+
+    pub fn misfire(&self) -> Misfire {
+        self.misfire
+    }
+
+    /// When the task last ran, `None` if it hasn't run yet
+    pub fn last_run(&self) -> Option<Timestamp> {
+        self.last_run
+    }
+
+    /// Records that the task last ran at `ts`, e.g. before a restart, and picks up from the first
+    /// run after it. The runs missed since are handled by the [Misfire] policy
+    /// ```
+    /// use scheduler::{Misfire, Scheduler};
+    /// use scheduler::time::AsTimeSpan;
+    /// # let saved = scheduler::time::timestamp::Timestamp::new(1_718_355_600);
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// scheduler
+    ///     .every(1.hour())
+    ///     .on_misfire(Misfire::FireAll)
+    ///     .last_ran_at(saved)
+    ///     .perform(|| println!("catching up!"));
+    /// ```
+    pub fn last_ran_at(&mut self, ts: Timestamp) -> &mut Self {
+        self.last_run = Some(ts);
+        let scheduled_on = ts - Duration::from_secs(self.offset);
+        // fixed rate durations run in multiples of the interval from the last run instead
+        if scheduled_on < self.anchor {
+            self.anchor = scheduled_on;
+        }
+        self.schedule_after(scheduled_on);
+        self
+    }
+
+    pub fn cadence(&self) -> Cadence {
+        self.cadence
+    }
+
+    /// The first run of the schedule, before any spread or jitter, see [Cadence::FixedRate]
+    pub fn anchor(&self) -> Timestamp {
+        self.anchor
+    }
+
+    /// Completes the schedule once its next run would fall after `ts`
+    pub fn until(&mut self, ts: Timestamp) -> &mut Self {
+        self.until = Some(ts);
+        self
+    }
+
+    /// Completes the schedule after it has run `n` times
+    pub fn times(&mut self, n: u32) -> &mut Self {
+        self.times = Some(n);
+        self
+    }
+
+    /// Previews the runs from `run_on` onwards, as if every run happened on time, without changing the
+    /// schedule. The iterator ends with the schedule and is endless otherwise. Runs with jitter are
+    /// previewed with offsets of their own, see [Schedule::with_jitter]
+    /// ```
+    /// use scheduler::Scheduler;
+    /// use scheduler::time::AsTimeSpan;
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// let schedule = scheduler.every(1.day().at("09:00").unwrap()).times(3);
+    /// for run_on in schedule.upcoming() {
+    ///     println!("{}", run_on.as_timestamp());
+    /// }
+    /// ```
+    pub fn upcoming(&self) -> Upcoming {
+        Upcoming {
+            ts: self.ts.clone(),
+            tz: self.tz.clone(),
+            spread: self.spread,
+            next: (!self.is_completed()).then_some((self.run_on, self.offset)),
+            until: self.until,
+            remaining: self.times.map(|times| times.saturating_sub(self.runs)),
+        }
+    }
+
+    /// How many times the task has run so far
+    pub fn runs(&self) -> u32 {
+        self.runs
+    }
+
+    pub fn task_id(&self) -> TaskId {
+        self.task.id()
+    }
+
+    /// A completed schedule never runs again and is retired by the [crate::Scheduler]
+    pub fn is_completed(&self) -> bool {
+        self.times.is_some_and(|times| self.runs >= times)
+            || self.until.is_some_and(|until| self.run_on > until)
+    }
+
     pub(crate) fn next_run_on(&mut self) -> &Timestamp {
-        let run_on = self.ts.next_run_on();
-        self.run_on = run_on;
+        // the span computes the next run from the current one as if it wasn't offset
+        let after = self.clock.now() - Duration::from_secs(self.offset);
+        self.schedule_after(after);
         &self.run_on
     }
+
+    fn schedule_after(&mut self, after: Timestamp) {
+        let next = match self.cadence {
+            Cadence::FixedRate if self.ts.is_duration() && self.ts.interval > 0 => {
+                let next = self.next_fixed_rate(&after);
+                self.ts.skip_excluded(next, &self.tz)
+            }
+            _ => self.ts.next_run_on_after(&after, &self.tz),
+        };
+        self.set_run_on(next);
+    }
+
+    /// Fixed delay durations compute their next run once the runs handed to the executor finish
+    pub(crate) fn awaits_completion(&self) -> bool {
+        self.cadence == Cadence::FixedDelay && self.ts.is_duration()
+    }
+
+    /// How many times the task runs now that it's due, according to the [Misfire] policy. The runs
+    /// that were missed are the ones since the last run, see [Schedule::last_ran_at]
+    pub(crate) fn due_runs(&self) -> usize {
+        let now = self.clock.now();
+        let mut missed = self
+            .upcoming()
+            .skip_while(|run_on| self.last_run.is_some_and(|last_run| *run_on <= last_run))
+            .take_while(|run_on| *run_on <= now)
+            .peekable();
+        let Some(earliest) = missed.peek().copied() else {
+            return 0;
+        };
+        let late = now.duration_since(&earliest).unwrap_or_default();
+        match self.misfire {
+            Misfire::FireOnce => 1,
+            Misfire::FireAll => missed.take(MAX_MISFIRED_RUNS).count(),
+            Misfire::Skip => (late <= MISFIRE_THRESHOLD) as usize,
+            Misfire::Grace(grace) => (late <= grace) as usize,
+        }
+    }
+
+    /// The first multiple of the interval from the anchor after `after`
+    fn next_fixed_rate(&self, after: &Timestamp) -> Timestamp {
+        if *after < self.anchor {
+            return self.anchor;
+        }
+        let interval = self.ts.interval as i128 * 1_000_000_000;
+        let periods = (after.as_nanos() - self.anchor.as_nanos()) / interval + 1;
+        Timestamp::from_nanos(self.anchor.as_nanos() + periods * interval)
+            .unwrap_or(Timestamp::new(Timestamp::MAX_SECS))
+    }
+
+    /// The run computed by the [TimeSpan], before it was offset
+    pub(crate) fn scheduled_on(&self) -> Timestamp {
+        self.run_on - Duration::from_secs(self.offset)
+    }
+
+    pub(crate) fn set_run_on(&mut self, scheduled_on: Timestamp) {
+        self.offset = self.spread.map_or(0, |spread| spread.offset(&scheduled_on));
+        self.run_on = scheduled_on + self.offset;
+    }
+
+    fn set_spread(&mut self, spread: Spread) -> &mut Self {
+        let scheduled_on = self.scheduled_on();
+        self.spread = Some(spread);
+        self.set_run_on(scheduled_on);
+        self
+    }
+}
+
+impl Spread {
+    /// The seconds to delay the run `scheduled_on` by
+    fn offset(&self, scheduled_on: &Timestamp) -> u64 {
+        match *self {
+            Spread::Jitter(0) | Spread::Hash(0, _) => 0,
+            Spread::Jitter(window) => {
+                RandomState::new().hash_one(scheduled_on.as_timestamp()) % window
+            }
+            Spread::Hash(window, hash) => hash % window,
+        }
+    }
+}
+
+impl Iterator for Upcoming {
+    type Item = Timestamp;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (run_on, offset) = self.next.take()?;
+        if self.remaining == Some(0) || self.until.is_some_and(|until| run_on > until) {
+            return None;
+        }
+        self.remaining = self.remaining.map(|remaining| remaining - 1);
+
+        let scheduled_on = run_on - Duration::from_secs(offset);
+        let next = self.ts.next_run_on_after(&scheduled_on, &self.tz);
+        let offset = self.spread.map_or(0, |spread| spread.offset(&next));
+        self.next = next.checked_add(Duration::from_secs(offset)).map(|next| (next, offset));
+
+        Some(run_on)
+    }
+}
+
+/// Describes the [TimeSpan] along with the bounds,
+/// e.g. "every hour until 2024-06-15T00:00:00Z, 3 times"
+impl Display for Schedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ts)?;
+        if let Some(until) = self.until {
+            write!(f, " until {until}")?;
+        }
+        match self.times {
+            Some(1) => write!(f, ", once"),
+            Some(times) => write!(f, ", {times} times"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 64-bit FNV-1a, unlike the std hashers its output never changes between runs or releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Cadence, MAX_MISFIRED_RUNS, Misfire, Scheduler, TaskId};
+    use crate::time::{AsTimeSpan, DAY_IN_SECS, HOUR_IN_SECS, MINUTE_IN_SECS, MockClock, SpanError};
+    use crate::time::timestamp::Timestamp;
+    use crate::time::WeekDay::Monday;
+
+    #[test]
+    fn test_spread() {
+        let mut scheduler = Scheduler::new();
+        let first = scheduler.every(1.hour()).spread_by("backup", 30.minute()).unwrap().offset;
+        let second = scheduler.every(1.hour()).spread_by("backup", 30.minute()).unwrap().offset;
+        let other = scheduler.every(1.hour()).spread_by("reports", 30.minute()).unwrap().offset;
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert!(first < 30 * MINUTE_IN_SECS && other < 30 * MINUTE_IN_SECS);
+
+        // the offset applies on top of the wall-clock time and is kept on every run
+        let schedule = scheduler.every(1.day().at("10:00").unwrap()).spread(1.hour()).unwrap();
+        let offset = schedule.offset;
+        for _ in 0..3 {
+            let run_on = schedule.run_on.as_timestamp() as u64;
+            assert_eq!((run_on - offset) % DAY_IN_SECS, 10 * HOUR_IN_SECS);
+            schedule.next_run_on();
+            assert_eq!(schedule.offset, offset);
+        }
+
+        let schedule = scheduler.every(1.hour());
+        assert_eq!(schedule.spread(1.month()).err(), Some(SpanError::NotADuration));
+        let schedule = scheduler.every(1.hour());
+        let not_a_duration = schedule.spread_by("backup", Monday.at("10:00").unwrap()).err();
+        assert_eq!(not_a_duration, Some(SpanError::NotADuration));
+    }
+
+    #[test]
+    fn test_jitter() {
+        let mut scheduler = Scheduler::new();
+        let schedule = scheduler.every(1.day().at("10:00").unwrap());
+        let schedule = schedule.with_jitter(5.minute()).unwrap();
+        for _ in 0..10 {
+            let run_on = schedule.run_on.as_timestamp() as u64;
+            assert!(schedule.offset < 5 * MINUTE_IN_SECS);
+            assert_eq!((run_on - schedule.offset) % DAY_IN_SECS, 10 * HOUR_IN_SECS);
+            schedule.next_run_on();
+        }
+
+        let schedule = scheduler.every(1.hour());
+        assert_eq!(schedule.with_jitter(1.month()).err(), Some(SpanError::NotADuration));
+    }
+
+    #[test]
+    fn test_upcoming() {
+        // 2024-06-14T09:00:00Z
+        let now = Timestamp::new(1_718_355_600);
+        let mut scheduler = Scheduler::new().with_clock(MockClock::new(now));
+        // daily spans first run on the next day
+        let at_ten = now.as_timestamp() + (DAY_IN_SECS + HOUR_IN_SECS) as i64;
+
+        let schedule = scheduler.every(1.day().at("10:00").unwrap());
+        let upcoming = schedule.upcoming().take(3).map(|ts| ts.as_timestamp()).collect::<Vec<_>>();
+        let day = DAY_IN_SECS as i64;
+        assert_eq!(upcoming, vec![at_ten, at_ten + day, at_ten + 2 * day]);
+        assert_eq!(schedule.run_on.as_timestamp(), at_ten);
+
+        let schedule = scheduler.every(1.hour()).times(2);
+        assert_eq!(schedule.upcoming().count(), 2);
+
+        let schedule = scheduler.every(1.hour()).until(now + 3 * HOUR_IN_SECS + 1);
+        assert_eq!(schedule.upcoming().count(), 3);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut scheduler = Scheduler::new().with_clock(MockClock::new(Timestamp::new(0)));
+        let schedule = scheduler.every(1.hour()).until(Timestamp::new(86_400)).times(3);
+        assert_eq!(schedule.to_string(), "every hour until 1970-01-02T00:00:00Z, 3 times");
+        assert_eq!(scheduler.every(2.day()).times(1).to_string(), "every 2 days, once");
+    }
+
+    #[test]
+    fn test_cadence() {
+        // 2024-06-14T09:00:00Z
+        let now = Timestamp::new(1_718_355_600);
+        let clock = MockClock::new(now);
+        let mut scheduler = Scheduler::new().with_clock(clock.clone());
+        let rate = scheduler.every(1.hour()).perform(|| {});
+        let delay = scheduler.every(1.hour()).fixed_delay().perform(|| {});
+
+        let anchor = now + HOUR_IN_SECS;
+        for schedule in scheduler.schedules.iter() {
+            assert_eq!(schedule.anchor(), anchor);
+        }
+
+        // the runs are 20 minutes late
+        clock.advance(Duration::from_secs(HOUR_IN_SECS + 20 * MINUTE_IN_SECS));
+        assert_eq!(scheduler.run_pending(), vec![rate, delay]);
+        let run_on = |scheduler: &Scheduler, cadence| {
+            let schedule = scheduler.schedules.iter().find(|sc| sc.cadence() == cadence).unwrap();
+            (schedule.run_on.as_timestamp() - now.as_timestamp()) / 60
+        };
+        assert_eq!(run_on(&scheduler, Cadence::FixedRate), 120);
+        assert_eq!(run_on(&scheduler, Cadence::FixedDelay), 140);
+
+        // missed runs are skipped without shifting the ones after them
+        clock.advance(Duration::from_secs(3 * HOUR_IN_SECS));
+        scheduler.run_pending();
+        assert_eq!(run_on(&scheduler, Cadence::FixedRate), 300);
+        assert_eq!(run_on(&scheduler, Cadence::FixedDelay), 320);
+
+        let starting = now + 10 * HOUR_IN_SECS;
+        assert_eq!(scheduler.every(1.hour()).starting(starting).anchor(), starting);
+    }
+
+    #[test]
+    fn test_misfire() {
+        // 2024-06-14T09:00:00Z, a Friday
+        let now = Timestamp::new(1_718_355_600);
+        let clock = MockClock::new(now);
+        let mut scheduler = Scheduler::new().with_clock(clock.clone());
+        let every_day = || 1.day().at("10:00").unwrap();
+        let once = scheduler.every(every_day()).perform(|| {});
+        let all = scheduler.every(every_day()).on_misfire(Misfire::FireAll).perform(|| {});
+        let skip = scheduler.every(every_day()).on_misfire(Misfire::Skip).perform(|| {});
+        let grace = Misfire::Grace(Duration::from_secs(HOUR_IN_SECS));
+        let grace = scheduler.every(every_day()).on_misfire(grace).perform(|| {});
+        let count = |ran: &[TaskId], id| ran.iter().filter(|ran| **ran == id).count();
+
+        // right on time
+        clock.advance(Duration::from_secs(DAY_IN_SECS + HOUR_IN_SECS));
+        let ran = scheduler.run_pending();
+        assert_eq!([once, all, skip, grace].map(|id| count(&ran, id)), [1, 1, 1, 1]);
+
+        // down over the weekend until Monday at 10:30, missing the runs of Sunday and Monday
+        clock.advance(Duration::from_secs(2 * DAY_IN_SECS + 30 * MINUTE_IN_SECS));
+        let ran = scheduler.run_pending();
+        assert_eq!([once, all, skip, grace].map(|id| count(&ran, id)), [1, 2, 0, 0]);
+
+        let last_run = scheduler.schedules[0].last_run();
+        assert_eq!(last_run, Some(scheduler.now()));
+        // every schedule continues with Tuesday at 10:00
+        for schedule in scheduler.schedules.iter() {
+            let tuesday = now + 4 * DAY_IN_SECS + HOUR_IN_SECS;
+            assert_eq!(schedule.run_on, tuesday);
+        }
+
+        // within the grace period but past the misfire threshold
+        clock.advance(Duration::from_secs(DAY_IN_SECS));
+        let ran = scheduler.run_pending();
+        assert_eq!([once, all, skip, grace].map(|id| count(&ran, id)), [1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_last_ran_at() {
+        // 2024-06-14T09:00:00Z
+        let now = Timestamp::new(1_718_355_600);
+        let mut scheduler = Scheduler::new().with_clock(MockClock::new(now));
+        let count = |ran: &[TaskId], id| ran.iter().filter(|ran| **ran == id).count();
+
+        // the tasks last ran three hours ago, before a restart
+        let last_run = now - Duration::from_secs(3 * HOUR_IN_SECS);
+        let once = scheduler.every(1.hour()).last_ran_at(last_run).perform(|| {});
+        let all = scheduler
+            .every(1.hour())
+            .on_misfire(Misfire::FireAll)
+            .last_ran_at(last_run)
+            .perform(|| {});
+        let skip = scheduler
+            .every(1.hour())
+            .on_misfire(Misfire::Skip)
+            .last_ran_at(last_run)
+            .perform(|| {});
+        let ran = scheduler.run_pending();
+        assert_eq!([once, all, skip].map(|id| count(&ran, id)), [1, 3, 0]);
+        for schedule in scheduler.schedules.iter() {
+            assert_eq!(schedule.anchor(), last_run);
+            assert_eq!(schedule.run_on, now + HOUR_IN_SECS);
+        }
+
+        // a day of missed runs is capped
+        let every_second = scheduler
+            .every(1.second())
+            .on_misfire(Misfire::FireAll)
+            .last_ran_at(now - Duration::from_secs(DAY_IN_SECS))
+            .perform(|| {});
+        let ran = scheduler.run_pending();
+        assert_eq!(count(&ran, every_second), MAX_MISFIRED_RUNS);
+        assert_eq!(scheduler.schedules[0].run_on, now + 1);
+    }
 }
@@ -0,0 +1,268 @@
+// Holiday calendars can be loaded from iCalendar files (RFC 5545) or from plain date lists with one
+// `YYYY-MM-DD` date per line, where empty lines and lines starting with `#` are ignored.
+//
+// Only the parts of iCalendar that describe holidays are read: the DTSTART and DTEND of every VEVENT
+// (DTEND is exclusive, as in the RFC) and yearly recurrence through `RRULE:FREQ=YEARLY`.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use crate::time::Date;
+
+/// Holidays are never business days and neither are Saturdays and Sundays
+#[derive(Default)]
+pub struct HolidayCalendar {
+    pub(crate) dates: BTreeSet<Date>,
+    /// Holidays that repeat every year, as `(month, day)`
+    pub(crate) yearly: BTreeSet<(u8, u8)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CalendarError {
+    /// The file could not be read
+    Io(String),
+    /// `line` is 1-based and refers to the (unfolded) line of the file
+    Invalid { line: usize, reason: &'static str },
+}
+
+impl HolidayCalendar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a calendar from `(year, month, day)` dates
+    pub fn from_dates<I>(dates: I) -> Self
+    where
+        I: IntoIterator<Item = (u32, u8, u8)>,
+    {
+        let mut calendar = Self::new();
+        for (year, month, day) in dates {
+            calendar.dates.insert(Date::new(year, month, day));
+        }
+        calendar
+    }
+
+    /// Reads a file with one `YYYY-MM-DD` date per line
+    pub fn from_date_list<P: AsRef<Path>>(path: P) -> Result<Self, CalendarError> {
+        Self::parse_date_list(&Self::read(path.as_ref())?)
+    }
+
+    /// Reads the holidays of an iCalendar (.ics) file
+    pub fn from_ics<P: AsRef<Path>>(path: P) -> Result<Self, CalendarError> {
+        Self::parse_ics(&Self::read(path.as_ref())?)
+    }
+
+    pub fn parse_date_list(src: &str) -> Result<Self, CalendarError> {
+        let mut calendar = Self::new();
+        for (idx, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let date = line.splitn(3, '-').collect::<Vec<_>>();
+            let date = match date[..] {
+                [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+                    Self::parse_date(year, month, day)
+                }
+                _ => None,
+            };
+            match date {
+                Some(date) => calendar.dates.insert(date),
+                None => return Err(Self::invalid(idx + 1, "expected a YYYY-MM-DD date")),
+            };
+        }
+
+        Ok(calendar)
+    }
+
+    pub fn parse_ics(src: &str) -> Result<Self, CalendarError> {
+        // long lines are folded by starting the continuation lines with a space or a tab
+        let mut lines: Vec<(usize, String)> = Vec::new();
+        for (idx, line) in src.lines().enumerate() {
+            match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+                (Some(rest), Some((_, last))) => last.push_str(rest.trim_end()),
+                _ => lines.push((idx + 1, line.trim_end().to_string())),
+            }
+        }
+
+        let mut calendar = Self::new();
+        let mut event: Option<(Option<Date>, Option<Date>, bool)> = None;
+        for (line_no, line) in lines {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let name = name.split(';').next().unwrap_or_default();
+
+            match (name, event.as_mut()) {
+                ("BEGIN", None) if value == "VEVENT" => event = Some((None, None, false)),
+                ("END", Some((start, end, yearly))) if value == "VEVENT" => {
+                    let Some(start) = *start else {
+                        return Err(Self::invalid(line_no, "event without DTSTART"));
+                    };
+                    calendar.insert_event(start, *end, *yearly);
+                    event = None;
+                }
+                ("DTSTART", Some((start, _, _))) => {
+                    start.replace(Self::parse_ics_date(value, line_no)?);
+                }
+                ("DTEND", Some((_, end, _))) => {
+                    end.replace(Self::parse_ics_date(value, line_no)?);
+                }
+                ("RRULE", Some((_, _, yearly))) => {
+                    *yearly = value.split(';').any(|part| part == "FREQ=YEARLY");
+                }
+                _ => {}
+            }
+        }
+
+        match event {
+            Some(_) => Err(Self::invalid(src.lines().count(), "unterminated VEVENT")),
+            None => Ok(calendar),
+        }
+    }
+
+    pub fn is_holiday(&self, date: (u32, u8, u8)) -> bool {
+        self.contains(&Date::new(date.0, date.1, date.2))
+    }
+
+    /// `true` for the weekdays from Monday to Friday that aren't holidays
+    pub fn is_business_day(&self, date: (u32, u8, u8)) -> bool {
+        self.is_business(&Date::new(date.0, date.1, date.2))
+    }
+
+    pub(crate) fn is_business(&self, date: &Date) -> bool {
+        !matches!(date.day_of_week(), 0 | 6) && !self.contains(date)
+    }
+
+    fn contains(&self, date: &Date) -> bool {
+        self.dates.contains(date) || self.yearly.contains(&(date.month, date.day))
+    }
+
+    /// `end` is exclusive, all-day events without one last a single day
+    fn insert_event(&mut self, start: Date, end: Option<Date>, yearly: bool) {
+        let mut date = start;
+        loop {
+            match yearly {
+                true => self.yearly.insert((date.month, date.day)),
+                false => self.dates.insert(date),
+            };
+            date = date.add_days(1);
+            if end.map_or(true, |end| date >= end) {
+                break;
+            }
+        }
+    }
+
+    /// Accepts both `YYYYMMDD` dates and `YYYYMMDDTHHMMSS[Z]` date-times, only the date is kept
+    fn parse_ics_date(value: &str, line: usize) -> Result<Date, CalendarError> {
+        let date = match value.get(..8) {
+            // the date is sliced by bytes, so anything but ASCII is rejected first
+            Some(date) if date.is_ascii() && (value.len() == 8 || value[8..].starts_with('T')) => {
+                Self::parse_date(&date[..4], &date[4..6], &date[6..])
+            }
+            _ => None,
+        };
+        date.ok_or(Self::invalid(line, "expected a YYYYMMDD date"))
+    }
+
+    pub(crate) fn parse_date(year: &str, month: &str, day: &str) -> Option<Date> {
+        let all_digits = [year, month, day]
+            .iter()
+            .all(|part| part.bytes().all(|b| b.is_ascii_digit()));
+        if !all_digits {
+            return None;
+        }
+
+        Date::checked(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+    }
+
+    fn read(path: &Path) -> Result<String, CalendarError> {
+        std::fs::read_to_string(path)
+            .map_err(|err| CalendarError::Io(format!("{}: {err}", path.display())))
+    }
+
+    fn invalid(line: usize, reason: &'static str) -> CalendarError {
+        CalendarError::Invalid { line, reason }
+    }
+}
+
+impl Display for CalendarError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(reason) => write!(f, "could not read calendar: {reason}"),
+            Self::Invalid { line, reason } => write!(f, "invalid calendar on line {line}: {reason}"),
+        }
+    }
+}
+
+impl Error for CalendarError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::calendar::{CalendarError, HolidayCalendar};
+
+    const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20241225\r
+DTEND;VALUE=DATE:20241227\r
+SUMMARY:Christmas\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20240101\r
+RRULE:FREQ=YEARLY\r
+SUMMARY:New Year's\r
+  Day\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20240415T090000Z\r
+SUMMARY:Company offsite\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn test_ics() {
+        let calendar = HolidayCalendar::parse_ics(ICS).unwrap();
+
+        assert!(calendar.is_holiday((2024, 12, 25)));
+        assert!(calendar.is_holiday((2024, 12, 26)));
+        assert!(!calendar.is_holiday((2024, 12, 27)));
+        assert!(calendar.is_holiday((2031, 1, 1)));
+        assert!(calendar.is_holiday((2024, 4, 15)));
+        assert!(!calendar.is_holiday((2025, 12, 25)));
+
+        for date in ["20241301", "202é456", "2024é456T0900"] {
+            let invalid = format!("BEGIN:VEVENT\nDTSTART;VALUE=DATE:{date}\nEND:VEVENT\n");
+            assert_eq!(
+                HolidayCalendar::parse_ics(&invalid).err(),
+                Some(CalendarError::Invalid {
+                    line: 2,
+                    reason: "expected a YYYYMMDD date"
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_date_list() {
+        let src = "# holidays\n2024-05-01\n\n2024-12-25\n";
+        let calendar = HolidayCalendar::parse_date_list(src).unwrap();
+        assert!(calendar.is_holiday((2024, 5, 1)));
+        assert!(!calendar.is_business_day((2024, 5, 1)));
+        // 2024-05-04 is a Saturday
+        assert!(!calendar.is_business_day((2024, 5, 4)));
+        assert!(calendar.is_business_day((2024, 5, 2)));
+
+        assert_eq!(
+            HolidayCalendar::parse_date_list("2024-05-01\n2024-5-1\n").err(),
+            Some(CalendarError::Invalid {
+                line: 2,
+                reason: "expected a YYYY-MM-DD date"
+            })
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::time::timestamp::Timestamp;
+
+/// The source of the current time for a [crate::Scheduler] and all of its schedules
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Timestamp;
+}
+
+/// Reads the time of the operating system, the default of every [crate::Scheduler]
+#[derive(Clone, Copy, Default)]
+pub struct SystemClock;
+
+/// A clock that only moves when told to, for deterministic tests. Clones share the same time, so a
+/// clone can be handed to the [crate::Scheduler] and the original advanced from the test
+/// ```
+/// use std::time::Duration;
+/// use scheduler::Scheduler;
+/// use scheduler::time::{AsTimeSpan, MockClock};
+/// use scheduler::time::timestamp::Timestamp;
+///
+/// let clock = MockClock::new(Timestamp::new(1_718_355_600)); // 2024-06-14T09:00:00Z
+/// let mut scheduler = Scheduler::new().with_clock(clock.clone());
+/// let id = scheduler.every(1.hour()).perform(|| println!("hourly!"));
+///
+/// assert!(scheduler.run_pending().is_empty());
+/// clock.advance(Duration::from_secs(3600));
+/// assert_eq!(scheduler.run_pending(), vec![id]);
+/// ```
+#[derive(Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<Timestamp>>,
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+impl MockClock {
+    pub fn new(now: Timestamp) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    pub fn set(&self, now: Timestamp) {
+        *self.lock() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.lock();
+        *now += by;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Timestamp> {
+        // the guarded value is a plain timestamp, so a panic while holding the lock can't corrupt it
+        self.now.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Timestamp {
+        *self.lock()
+    }
+}
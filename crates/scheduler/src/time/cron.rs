@@ -0,0 +1,586 @@
+// Cron expressions follow the crontab(5) format with an optional leading seconds field:
+//
+// field          allowed values
+// -----          --------------
+// second         0-59 (only when 6 fields are given)
+// minute         0-59
+// hour           0-23
+// day of month   1-31
+// month          1-12 (or names, JAN-DEC)
+// day of week    0-7 (0 or 7 is Sunday, or use names, SUN-SAT)
+//
+// Each field accepts `*`, single values, ranges (`9-17`), steps (`*/15`, `10-40/5`, `5/10`) and
+// comma separated lists of any of the above.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::time::{DAY_IN_SECS, HOUR_IN_SECS, MINUTE_IN_SECS, Step, TimeSpan, TimeZone};
+use crate::time::timestamp::Timestamp;
+use crate::time::ts_components::TSComponents;
+
+const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// The longest gap between two occurrences of a valid expression is the 29th of February across a
+/// skipped leap year (e.g. 2096 -> 2104), so searching further than this means it never fires
+const MAX_SEARCH_YEARS: u32 = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CronField {
+    Second,
+    Minute,
+    Hour,
+    DayOfMonth,
+    Month,
+    DayOfWeek,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CronError {
+    /// The expression did not have 5 or 6 whitespace separated fields
+    FieldCount(usize),
+    /// `position` is the byte offset, in the whole expression, of the first invalid character
+    InvalidField {
+        field: CronField,
+        position: usize,
+        reason: &'static str,
+    },
+}
+
+/// A parsed cron expression. Every field is stored as a bit set of its allowed values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    seconds: u64,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// `true` when the day of month field is `*`
+    any_day_of_month: bool,
+    /// `true` when the day of week field is `*`
+    any_day_of_week: bool,
+}
+
+/// Parses a cron expression into a [TimeSpan] that can be passed to [crate::Scheduler::every]
+/// ```
+/// use scheduler::{Scheduler, time::cron};
+/// let mut scheduler = Scheduler::new();
+///
+/// scheduler
+///     .every(cron("*/15 9-17 * * MON-FRI").unwrap())
+///     .perform(|| println!("every 15 minutes during working hours!"));
+/// ```
+pub fn cron(expr: &str) -> Result<TimeSpan, CronError> {
+    expr.parse::<Cron>().map(TimeSpan::from)
+}
+
+impl CronField {
+    fn bounds(&self) -> (u8, u8) {
+        match self {
+            Self::Second | Self::Minute => (0, 59),
+            Self::Hour => (0, 23),
+            Self::DayOfMonth => (1, 31),
+            Self::Month => (1, 12),
+            Self::DayOfWeek => (0, 7),
+        }
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        match self {
+            Self::Month => &MONTH_NAMES,
+            Self::DayOfWeek => &WEEKDAY_NAMES,
+            _ => &[],
+        }
+    }
+
+    /// Names are offset by the first allowed value, e.g. JAN is 1 but SUN is 0
+    fn name_offset(&self) -> u8 {
+        match self {
+            Self::Month => 1,
+            _ => 0,
+        }
+    }
+}
+
+impl Cron {
+    /// Returns the first instant strictly after `after` whose wall-clock time in `tz` matches the
+    /// expression, or `None` if there is no such instant within [MAX_SEARCH_YEARS].
+    ///
+    /// Matching local times that are skipped by a DST transition fire at the transition and local
+    /// times that are repeated fire only once
+    pub fn next_after(&self, after: &Timestamp, tz: &TimeZone) -> Option<Timestamp> {
+        let mut local = tz.to_local(after).as_timestamp() + 1;
+        loop {
+            let candidate = self.next_local(local)?;
+            let next = tz.to_utc(&Timestamp::from_secs(candidate)?);
+            if next > *after {
+                return Some(next);
+            }
+            local = candidate + 1;
+        }
+    }
+
+    /// Returns the first wall-clock time, as seconds since the epoch, at or after `ts` that matches
+    fn next_local(&self, mut ts: i64) -> Option<i64> {
+        let limit = TSComponents::from(ts).year + MAX_SEARCH_YEARS;
+
+        loop {
+            let components = TSComponents::from(ts);
+            if components.year > limit {
+                return None;
+            }
+
+            if !Self::contains(self.months, components.month) {
+                let (year, month) = match components.month {
+                    12 => (components.year + 1, 1),
+                    month => (components.year, month + 1),
+                };
+                ts = TSComponents::new(year, month, 1).as_timestamp();
+                continue;
+            }
+
+            if !self.matches_day(components.day, Self::day_of_week(ts)) {
+                ts = Self::next_multiple(ts, DAY_IN_SECS);
+                continue;
+            }
+
+            if !Self::contains(self.hours, components.hour) {
+                ts = Self::next_multiple(ts, HOUR_IN_SECS);
+                continue;
+            }
+
+            if !Self::contains(self.minutes, components.minute) {
+                ts = Self::next_multiple(ts, MINUTE_IN_SECS);
+                continue;
+            }
+
+            if !Self::contains(self.seconds, components.second) {
+                ts += 1;
+                continue;
+            }
+
+            return Some(ts);
+        }
+    }
+
+    /// Mirrors cron: when both day fields are restricted, a day matches if *either* of them does
+    fn matches_day(&self, day: u8, weekday: u8) -> bool {
+        let dom = Self::contains(self.days_of_month, day);
+        let dow = Self::contains(self.days_of_week, weekday);
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => dow,
+            (false, true) => dom,
+            (false, false) => dom || dow,
+        }
+    }
+
+    fn day_of_week(ts: i64) -> u8 {
+        (ts.div_euclid(DAY_IN_SECS as i64) + 4).rem_euclid(7) as u8
+    }
+
+    /// The start of the day, hour or minute after the one `ts` falls in
+    fn next_multiple(ts: i64, secs: u64) -> i64 {
+        let secs = secs as i64;
+        ts - ts.rem_euclid(secs) + secs
+    }
+
+    /// `*` for every value, otherwise a comma separated list of values and ranges, e.g. `1-5,7`
+    fn field_to_string(set: u64, field: CronField, any: bool) -> String {
+        let (min, max) = match field {
+            // 7 is parsed as Sunday (0)
+            CronField::DayOfWeek => (0, 6),
+            field => field.bounds(),
+        };
+        // a restricted day field that allows every day still changes how the other one matches
+        let is_day = matches!(field, CronField::DayOfMonth | CronField::DayOfWeek);
+        if any || (!is_day && (min..=max).all(|value| Self::contains(set, value))) {
+            return "*".to_string();
+        }
+
+        let mut parts = Vec::new();
+        let mut value = min;
+        while value <= max {
+            if !Self::contains(set, value) {
+                value += 1;
+                continue;
+            }
+            let start = value;
+            while value < max && Self::contains(set, value + 1) {
+                value += 1;
+            }
+            parts.push(match start == value {
+                true => start.to_string(),
+                false => format!("{start}-{value}"),
+            });
+            value += 1;
+        }
+
+        parts.join(",")
+    }
+
+    fn contains(set: u64, value: u8) -> bool {
+        set & (1 << value) != 0
+    }
+
+    /// Parses a single field starting at byte `offset` of the whole expression
+    fn parse_field(field: CronField, src: &str, offset: usize) -> Result<u64, CronError> {
+        let mut set = 0;
+        let mut position = offset;
+        for part in src.split(',') {
+            set |= Self::parse_part(field, part, position)?;
+            position += part.len() + 1;
+        }
+
+        // 7 is an alias for Sunday
+        if field == CronField::DayOfWeek && Self::contains(set, 7) {
+            set = (set | 1) & !(1 << 7);
+        }
+
+        Ok(set)
+    }
+
+    fn parse_part(field: CronField, part: &str, offset: usize) -> Result<u64, CronError> {
+        let (min, max) = field.bounds();
+        let invalid = |position: usize, reason: &'static str| CronError::InvalidField {
+            field,
+            position,
+            reason,
+        };
+
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step_offset = offset + range.len() + 1;
+                let step = match step.parse::<u8>() {
+                    Ok(0) => return Err(invalid(step_offset, "step must be greater than 0")),
+                    Ok(step) => step,
+                    Err(_) => return Err(invalid(step_offset, "expected a numeric step")),
+                };
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            let start_value = Self::parse_value(field, start, offset)?;
+            let end_offset = offset + start.len() + 1;
+            let end_value = Self::parse_value(field, end, end_offset)?;
+            if start_value > end_value {
+                return Err(invalid(end_offset, "range end is smaller than its start"));
+            }
+            (start_value, end_value)
+        } else {
+            let value = Self::parse_value(field, range, offset)?;
+            // `5/10` means every 10 starting from 5
+            (value, step.map_or(value, |_| max))
+        };
+
+        let mut set = 0;
+        let mut value = start;
+        while value <= end {
+            set |= 1 << value;
+            match value.checked_add(step.unwrap_or(1)) {
+                Some(next) => value = next,
+                None => break,
+            }
+        }
+
+        Ok(set)
+    }
+
+    fn parse_value(field: CronField, src: &str, offset: usize) -> Result<u8, CronError> {
+        let (min, max) = field.bounds();
+        let invalid = |position: usize, reason: &'static str| CronError::InvalidField {
+            field,
+            position,
+            reason,
+        };
+
+        if src.is_empty() {
+            return Err(invalid(offset, "expected a value"));
+        }
+
+        if let Some(idx) = field
+            .names()
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(src))
+        {
+            return Ok(idx as u8 + field.name_offset());
+        }
+
+        if let Some(idx) = src.find(|c: char| !c.is_ascii_digit()) {
+            return Err(invalid(offset + idx, "unexpected character"));
+        }
+
+        match src.parse::<u8>() {
+            Ok(value) if (min..=max).contains(&value) => Ok(value),
+            _ => Err(invalid(offset, "value out of range")),
+        }
+    }
+
+    /// Rejects day of month/month combinations that can never occur, such as `30 2`
+    fn validate_days(&self, offset: usize) -> Result<(), CronError> {
+        if self.any_day_of_month || !self.any_day_of_week {
+            return Ok(());
+        }
+
+        let possible = (1..=12)
+            .filter(|month| Self::contains(self.months, *month))
+            .any(|month| {
+                (1..=TSComponents::days_in_month(2000, month) as u8)
+                    .any(|day| Self::contains(self.days_of_month, day))
+            });
+
+        match possible {
+            true => Ok(()),
+            false => Err(CronError::InvalidField {
+                field: CronField::DayOfMonth,
+                position: offset,
+                reason: "day never occurs in the given months",
+            }),
+        }
+    }
+}
+
+impl FromStr for Cron {
+    type Err = CronError;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        // keep the byte offset of every field so that errors can point at the exact character
+        let mut fields = Vec::with_capacity(6);
+        let mut start = None;
+        for (idx, c) in expr.char_indices().chain([(expr.len(), ' ')]) {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(idx),
+                (true, Some(s)) => {
+                    fields.push((s, &expr[s..idx]));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+
+        let kinds: &[CronField] = match fields.len() {
+            5 => &[
+                CronField::Minute,
+                CronField::Hour,
+                CronField::DayOfMonth,
+                CronField::Month,
+                CronField::DayOfWeek,
+            ],
+            6 => &[
+                CronField::Second,
+                CronField::Minute,
+                CronField::Hour,
+                CronField::DayOfMonth,
+                CronField::Month,
+                CronField::DayOfWeek,
+            ],
+            count => return Err(CronError::FieldCount(count)),
+        };
+
+        let mut sets = [1; 6]; // the seconds field defaults to 0
+        let skip = 6 - kinds.len();
+        for (idx, (kind, (offset, src))) in kinds.iter().zip(&fields).enumerate() {
+            sets[idx + skip] = Self::parse_field(*kind, src, *offset)?;
+        }
+
+        let day_of_month = fields[fields.len() - 3];
+        let cron = Cron {
+            seconds: sets[0],
+            minutes: sets[1],
+            hours: sets[2],
+            days_of_month: sets[3],
+            months: sets[4],
+            days_of_week: sets[5],
+            any_day_of_month: day_of_month.1 == "*",
+            any_day_of_week: fields[fields.len() - 1].1 == "*",
+        };
+        cron.validate_days(day_of_month.0)?;
+
+        Ok(cron)
+    }
+}
+
+impl From<Cron> for TimeSpan {
+    fn from(cron: Cron) -> Self {
+        TimeSpan::new(0, Step::Cron(cron))
+    }
+}
+
+/// Writes the canonical form of the expression, which parses back into an equal [Cron]. The
+/// seconds field is left out when it's only 0, e.g. `0 9 * * 1-5`
+impl Display for Cron {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.seconds != 1 {
+            write!(f, "{} ", Self::field_to_string(self.seconds, CronField::Second, false))?;
+        }
+        write!(
+            f,
+            "{} {} {} {} {}",
+            Self::field_to_string(self.minutes, CronField::Minute, false),
+            Self::field_to_string(self.hours, CronField::Hour, false),
+            Self::field_to_string(self.days_of_month, CronField::DayOfMonth, self.any_day_of_month),
+            Self::field_to_string(self.months, CronField::Month, false),
+            Self::field_to_string(self.days_of_week, CronField::DayOfWeek, self.any_day_of_week),
+        )
+    }
+}
+
+impl Display for CronField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Second => "second",
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::DayOfMonth => "day of month",
+            Self::Month => "month",
+            Self::DayOfWeek => "day of week",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl Display for CronError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FieldCount(count) => write!(f, "expected 5 or 6 fields, found {count}"),
+            Self::InvalidField {
+                field,
+                position,
+                reason,
+            } => write!(f, "invalid {field} field at position {position}: {reason}"),
+        }
+    }
+}
+
+impl Error for CronError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::cron::{Cron, CronError, CronField};
+    use crate::time::timestamp::Timestamp;
+    use crate::time::TimeZone;
+    use crate::time::ts_components::TSComponents;
+
+    fn at(year: u32, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Timestamp {
+        let mut components = TSComponents::new(year, month, day);
+        components.hour = hour;
+        components.minute = minute;
+        components.second = second;
+        Timestamp::new(components.as_timestamp())
+    }
+
+    fn next(expr: &str, after: Timestamp) -> Timestamp {
+        expr.parse::<Cron>()
+            .unwrap()
+            .next_after(&after, &TimeZone::utc())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2024-06-14 is a Friday
+        let cases = [
+            ("*/15 9-17 * * MON-FRI", at(2024, 6, 14, 17, 50, 0), at(2024, 6, 17, 9, 0, 0)),
+            ("*/15 9-17 * * MON-FRI", at(2024, 6, 14, 9, 0, 0), at(2024, 6, 14, 9, 15, 0)),
+            ("0 0 1 1 *", at(2024, 6, 14, 0, 0, 0), at(2025, 1, 1, 0, 0, 0)),
+            ("30 */10 * * * *", at(2024, 6, 14, 0, 0, 31), at(2024, 6, 14, 0, 10, 30)),
+            ("0 12 29 FEB *", at(2024, 3, 1, 0, 0, 0), at(2028, 2, 29, 12, 0, 0)),
+            ("0 0 13 * FRI", at(2024, 6, 1, 0, 0, 0), at(2024, 6, 7, 0, 0, 0)),
+            ("5/20 0 * * 7", at(2024, 6, 14, 0, 0, 0), at(2024, 6, 16, 0, 5, 0)),
+            ("0 0 31 * *", at(2024, 4, 1, 0, 0, 0), at(2024, 5, 31, 0, 0, 0)),
+            ("0 8 * dec,jan sun", at(2024, 6, 14, 0, 0, 0), at(2024, 12, 1, 8, 0, 0)),
+        ];
+
+        for (expr, after, expected) in cases {
+            assert_eq!(
+                next(expr, after).as_timestamp(),
+                expected.as_timestamp(),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("* * * *", CronError::FieldCount(4)),
+            (
+                "*/15 9-17 * * MON-FRX",
+                CronError::InvalidField {
+                    field: CronField::DayOfWeek,
+                    position: 18,
+                    reason: "unexpected character",
+                },
+            ),
+            (
+                "61 * * * *",
+                CronError::InvalidField {
+                    field: CronField::Minute,
+                    position: 0,
+                    reason: "value out of range",
+                },
+            ),
+            (
+                "0  17-9 * * *",
+                CronError::InvalidField {
+                    field: CronField::Hour,
+                    position: 6,
+                    reason: "range end is smaller than its start",
+                },
+            ),
+            (
+                "0 0 * * 1/0",
+                CronError::InvalidField {
+                    field: CronField::DayOfWeek,
+                    position: 10,
+                    reason: "step must be greater than 0",
+                },
+            ),
+            (
+                "0 0 30 2 *",
+                CronError::InvalidField {
+                    field: CronField::DayOfMonth,
+                    position: 4,
+                    reason: "day never occurs in the given months",
+                },
+            ),
+            (
+                "0 0 1,,2 * *",
+                CronError::InvalidField {
+                    field: CronField::DayOfMonth,
+                    position: 6,
+                    reason: "expected a value",
+                },
+            ),
+        ];
+
+        for (expr, expected) in cases {
+            assert_eq!(expr.parse::<Cron>().unwrap_err(), expected, "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let cases = [
+            ("0 9 * * MON-FRI", "0 9 * * 1-5"),
+            ("*/15 9-17 * * *", "0,15,30,45 9-17 * * *"),
+            ("30 0 0 1,15 * 7", "30 0 0 1,15 * 0"),
+            ("0 0 1-31 JAN,FEB,MAR 1", "0 0 1-31 1-3 1"),
+        ];
+        for (expr, expected) in cases {
+            let cron = expr.parse::<Cron>().unwrap();
+            assert_eq!(cron.to_string(), expected);
+            assert_eq!(expected.parse::<Cron>(), Ok(cron));
+        }
+    }
+}
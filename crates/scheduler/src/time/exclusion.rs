@@ -0,0 +1,154 @@
+use crate::time::{Date, DAY_IN_SECS, SpanError, Time, TimeError, WeekDay};
+use crate::time::timestamp::Timestamp;
+use crate::time::ts_components::TSComponents;
+
+/// A blackout that a [crate::time::TimeSpan] never runs in, see [crate::time::TimeSpan::except].
+/// Every exclusion refers to the wall-clock time in the timezone of the [crate::Scheduler]
+#[derive(Clone)]
+pub struct Exclusion(pub(crate) ExclusionKind);
+
+#[derive(Clone)]
+pub(crate) enum ExclusionKind {
+    Weekday(WeekDay),
+    /// Both dates are included
+    Dates(Date, Date),
+    /// Times of day from the first (included) to the second (excluded), may wrap around midnight
+    Window(Time, Time),
+}
+
+impl Exclusion {
+    pub fn weekday(day: WeekDay) -> Self {
+        Self(ExclusionKind::Weekday(day))
+    }
+
+    /// A single date as `(year, month, day)`, e.g. `Exclusion::date((2024, 12, 25))`
+    pub fn date(date: (u32, u8, u8)) -> Result<Self, SpanError> {
+        Self::dates(date, date)
+    }
+
+    /// Every date from `from` through `to` as `(year, month, day)`, fails unless both are dates and
+    /// `from` isn't after `to`
+    pub fn dates(from: (u32, u8, u8), to: (u32, u8, u8)) -> Result<Self, SpanError> {
+        let date = |(year, month, day)| {
+            Date::checked(year, month, day).ok_or(SpanError::Date(year, month, day))
+        };
+        let (first, last) = (date(from)?, date(to)?);
+        if first > last {
+            return Err(SpanError::DateRange(from, to));
+        }
+        Ok(Self(ExclusionKind::Dates(first, last)))
+    }
+
+    /// Every day from `from` until `to`, e.g. `Exclusion::between("22:00", "06:00")` for nights
+    pub fn between(from: &str, to: &str) -> Result<Self, TimeError> {
+        Ok(Self(ExclusionKind::Window(
+            Time::from_time_str(from)?,
+            Time::from_time_str(to)?,
+        )))
+    }
+
+    /// If the wall-clock `local` time is excluded, returns the wall-clock time the blackout ends at
+    pub(crate) fn excluded_until(&self, local: &Timestamp) -> Option<Timestamp> {
+        let (ts, day) = (local.as_timestamp(), DAY_IN_SECS as i64);
+        let midnight = ts - ts.rem_euclid(day);
+        let until = match self.0 {
+            ExclusionKind::Weekday(weekday) => {
+                (local.day_of_week() == weekday as u8).then_some(midnight + day)
+            }
+            ExclusionKind::Dates(from, to) => {
+                let date = Date::from(&TSComponents::from(ts));
+                (from <= date && date <= to)
+                    .then(|| TSComponents::new(to.year, to.month, to.day).as_timestamp() + day)
+            }
+            ExclusionKind::Window(from, to) => {
+                let (from, to, now) = (from.as_secs() as i64, to.as_secs() as i64, ts - midnight);
+                match from <= to {
+                    true => (from <= now && now < to).then_some(midnight + to),
+                    false if now >= from => Some(midnight + day + to),
+                    false => (now < to).then_some(midnight + to),
+                }
+            }
+        };
+
+        until.map(Timestamp::new)
+    }
+}
+
+impl From<WeekDay> for Exclusion {
+    fn from(day: WeekDay) -> Self {
+        Self::weekday(day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::{AsTimeSpan, Exclusion, SpanError, TimeSpan, TimeZone};
+    use crate::time::timestamp::Timestamp;
+    use crate::time::ts_components::TSComponents;
+    use crate::time::WeekDay::{Monday, Saturday, Sunday};
+
+    fn at(year: u32, month: u8, day: u8, hour: u8, minute: u8) -> Timestamp {
+        let mut components = TSComponents::new(year, month, day);
+        components.hour = hour;
+        components.minute = minute;
+        Timestamp::new(components.as_timestamp())
+    }
+
+    fn skip(ts: &mut TimeSpan, next: Timestamp) -> (u32, u8, u8, u8, u8) {
+        let c = TSComponents::from(ts.skip_excluded(next, &TimeZone::utc()).as_timestamp());
+        (c.year, c.month, c.day, c.hour, c.minute)
+    }
+
+    fn until(exclusion: &Exclusion, local: Timestamp) -> Option<i64> {
+        exclusion.excluded_until(&local).map(|ts| ts.as_timestamp())
+    }
+
+    #[test]
+    fn test_excluded_until() {
+        let secs = |year, month, day, hour, minute| {
+            Some(at(year, month, day, hour, minute).as_timestamp())
+        };
+
+        // 2024-06-15 is a Saturday
+        let saturday = Exclusion::from(Saturday);
+        assert_eq!(until(&saturday, at(2024, 6, 14, 23, 59)), None);
+        assert_eq!(until(&saturday, at(2024, 6, 15, 10, 0)), secs(2024, 6, 16, 0, 0));
+
+        let night = Exclusion::between("22:00", "06:00").unwrap();
+        assert_eq!(until(&night, at(2024, 6, 15, 12, 0)), None);
+        assert_eq!(until(&night, at(2024, 6, 15, 23, 0)), secs(2024, 6, 16, 6, 0));
+        assert_eq!(until(&night, at(2024, 6, 15, 1, 0)), secs(2024, 6, 15, 6, 0));
+
+        let freeze = Exclusion::dates((2024, 12, 20), (2025, 1, 6)).unwrap();
+        assert_eq!(until(&freeze, at(2024, 12, 19, 23, 59)), None);
+        assert_eq!(until(&freeze, at(2024, 12, 31, 9, 0)), secs(2025, 1, 7, 0, 0));
+        assert_eq!(until(&freeze, at(2025, 1, 7, 0, 0)), None);
+
+        assert_eq!(Exclusion::date((2024, 2, 30)).err(), Some(SpanError::Date(2024, 2, 30)));
+        assert_eq!(Exclusion::date((2024, 13, 1)).err(), Some(SpanError::Date(2024, 13, 1)));
+        assert_eq!(
+            Exclusion::dates((2025, 1, 6), (2024, 12, 20)).err(),
+            Some(SpanError::DateRange((2025, 1, 6), (2024, 12, 20)))
+        );
+    }
+
+    #[test]
+    fn test_skip_excluded() {
+        // durations keep their phase after the blackout
+        let mut hourly = 1.hour().except(Saturday).except(Sunday);
+        assert_eq!(skip(&mut hourly, at(2024, 6, 15, 10, 17)), (2024, 6, 17, 0, 17));
+        assert_eq!(skip(&mut hourly, at(2024, 6, 14, 10, 17)), (2024, 6, 14, 10, 17));
+
+        let christmas = Exclusion::date((2024, 12, 25)).unwrap();
+        let mut daily = 1.day().at("10:00").unwrap().except(christmas);
+        assert_eq!(skip(&mut daily, at(2024, 12, 25, 10, 0)), (2024, 12, 26, 10, 0));
+
+        // overlapping exclusions are all skipped
+        let mut mondays = Monday
+            .at("09:00")
+            .unwrap()
+            .except(Exclusion::dates((2024, 12, 20), (2025, 1, 6)).unwrap())
+            .except(Exclusion::date((2025, 1, 13)).unwrap());
+        assert_eq!(skip(&mut mondays, at(2024, 12, 23, 9, 0)), (2025, 1, 20, 9, 0));
+    }
+}
@@ -0,0 +1,401 @@
+// Timestamps are displayed as RFC 3339 date-times in UTC, e.g. `2024-06-14T09:00:00Z`, with as many
+// fractional digits (3, 6 or 9) as needed for the sub-second part.
+//
+// Parsing accepts ISO 8601 dates and date-times in the extended (`2024-06-14T09:00:00.5+03:00`) and
+// the basic (`20240614T090000Z`) format. A date alone is midnight and a date-time without an offset
+// is in UTC. The date and time may also be separated by a space, as RFC 3339 allows.
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter, Write};
+use std::str::FromStr;
+
+use crate::time::{DAY_IN_SECS, HOUR_IN_SECS, MINUTE_IN_SECS, TimeZone};
+use crate::time::timestamp::Timestamp;
+use crate::time::ts_components::TSComponents;
+
+pub(crate) const MONTH_NAMES: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September",
+    "October", "November", "December",
+];
+pub(crate) const WEEKDAY_NAMES: [&str; 7] = [
+    "Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampError {
+    /// `position` is the byte offset of the first invalid character
+    Invalid { position: usize, reason: &'static str },
+    /// The date-time is valid but falls outside of the years 0 to 9999
+    OutOfRange,
+}
+
+impl Timestamp {
+    /// RFC 3339 in the wall-clock time of `tz` along with its offset, e.g.
+    /// `2024-06-14T12:00:00+03:00`. UTC is written as `Z`, like [Display] does
+    pub fn to_rfc3339_in(&self, tz: &TimeZone) -> String {
+        match tz.offset_at(self) {
+            0 => self.to_string(),
+            _ => self.format_in("%Y-%m-%dT%H:%M:%S%.f%:z", tz),
+        }
+    }
+
+    /// Formats the UTC date and time, see [Timestamp::format_in] for the specifiers
+    pub fn format(&self, fmt: &str) -> String {
+        self.format_in(fmt, &TimeZone::utc())
+    }
+
+    /// Formats the wall-clock date and time in `tz` with strftime-like specifiers:
+    ///
+    /// | specifier | example | |
+    /// |---|---|---|
+    /// | `%Y` `%y` `%C` | `2024` `24` `20` | year, year without and with only the century |
+    /// | `%m` `%B` `%b` | `06` `June` `Jun` | month |
+    /// | `%d` `%e` `%j` | `04` ` 4` `156` | day of the month, space padded and of the year |
+    /// | `%G` `%V` | `2024` `23` | ISO 8601 week-numbering year and week |
+    /// | `%A` `%a` `%w` `%u` | `Tuesday` `Tue` `2` `2` | weekday, from Sunday (0) or Monday (1) |
+    /// | `%H` `%I` `%p` | `21` `09` `PM` | hour, 12-hour clock hour and AM/PM |
+    /// | `%M` `%S` | `05` `09` | minute and second |
+    /// | `%f` `%.f` | `500000000` `.500` | nanoseconds and the fraction as in [Display] |
+    /// | `%z` `%:z` `%Z` | `+0300` `+03:00` `Europe/Athens` | offset and name of `tz` |
+    /// | `%s` | `1718355600` | seconds since the unix epoch |
+    /// | `%F` `%T` `%R` | `2024-06-04` `21:05:09` `21:05` | `%Y-%m-%d`, `%H:%M:%S` and `%H:%M` |
+    /// | `%n` `%t` `%%` | | newline, tab and `%` |
+    ///
+    /// Any other specifier is written as is
+    pub fn format_in(&self, fmt: &str, tz: &TimeZone) -> String {
+        let offset = tz.offset_at(self);
+        let local = tz.to_local(self);
+        let c = TSComponents::from(local.as_timestamp());
+        let (sign, offset_minutes) = match offset < 0 {
+            true => ('-', offset.unsigned_abs() / 60),
+            false => ('+', offset as u32 / 60),
+        };
+
+        let mut out = String::with_capacity(fmt.len() * 2);
+        let mut chars = fmt.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '%' {
+                out.push(ch);
+                continue;
+            }
+
+            // `write!` to a `String` never fails
+            let _ = match chars.next() {
+                Some('Y') => write!(out, "{:04}", c.year),
+                Some('y') => write!(out, "{:02}", c.year % 100),
+                Some('C') => write!(out, "{:02}", c.year / 100),
+                Some('m') => write!(out, "{:02}", c.month),
+                Some('B') => write!(out, "{}", MONTH_NAMES[c.month as usize - 1]),
+                Some('b') => write!(out, "{}", &MONTH_NAMES[c.month as usize - 1][..3]),
+                Some('d') => write!(out, "{:02}", c.day),
+                Some('e') => write!(out, "{:2}", c.day),
+                Some('j') => write!(out, "{:03}", Self::day_of_year(&c)),
+                Some('G') => write!(out, "{}", c.iso_week().0),
+                Some('V') => write!(out, "{:02}", c.iso_week().1),
+                Some('A') => write!(out, "{}", WEEKDAY_NAMES[local.day_of_week() as usize]),
+                Some('a') => write!(out, "{}", &WEEKDAY_NAMES[local.day_of_week() as usize][..3]),
+                Some('w') => write!(out, "{}", local.day_of_week()),
+                Some('u') => write!(out, "{}", (local.day_of_week() + 6) % 7 + 1),
+                Some('H') => write!(out, "{:02}", c.hour),
+                Some('I') => write!(out, "{:02}", (c.hour + 11) % 12 + 1),
+                Some('p') => write!(out, "{}", if c.hour < 12 { "AM" } else { "PM" }),
+                Some('M') => write!(out, "{:02}", c.minute),
+                Some('S') => write!(out, "{:02}", c.second),
+                Some('f') => write!(out, "{:09}", self.subsec_nanos()),
+                Some('s') => write!(out, "{}", self.as_timestamp()),
+                Some('z') => {
+                    write!(out, "{sign}{:02}{:02}", offset_minutes / 60, offset_minutes % 60)
+                }
+                Some('Z') => write!(out, "{}", tz.name()),
+                Some('F') => write!(out, "{:04}-{:02}-{:02}", c.year, c.month, c.day),
+                Some('T') => write!(out, "{:02}:{:02}:{:02}", c.hour, c.minute, c.second),
+                Some('R') => write!(out, "{:02}:{:02}", c.hour, c.minute),
+                Some('n') => writeln!(out),
+                Some('t') => write!(out, "\t"),
+                Some('%') => write!(out, "%"),
+                Some(':') if chars.as_str().starts_with('z') => {
+                    chars.next();
+                    write!(out, "{sign}{:02}:{:02}", offset_minutes / 60, offset_minutes % 60)
+                }
+                Some('.') if chars.as_str().starts_with('f') => {
+                    chars.next();
+                    Self::write_fraction(&mut out, self.subsec_nanos())
+                }
+                Some(other) => write!(out, "%{other}"),
+                None => write!(out, "%"),
+            };
+        }
+
+        out
+    }
+
+    /// Writes nothing for whole seconds, otherwise the fraction with 3, 6 or 9 digits
+    fn write_fraction(out: &mut impl Write, nanos: u32) -> std::fmt::Result {
+        match nanos {
+            0 => Ok(()),
+            _ if nanos % 1_000_000 == 0 => write!(out, ".{:03}", nanos / 1_000_000),
+            _ if nanos % 1_000 == 0 => write!(out, ".{:06}", nanos / 1_000),
+            _ => write!(out, ".{:09}", nanos),
+        }
+    }
+
+    fn day_of_year(c: &TSComponents) -> u64 {
+        let jan_first = TSComponents::new(c.year, 1, 1).as_timestamp();
+        let midnight = TSComponents::new(c.year, c.month, c.day).as_timestamp();
+        (midnight - jan_first) as u64 / DAY_IN_SECS + 1
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let c = TSComponents::from(self.as_timestamp());
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            c.year, c.month, c.day, c.hour, c.minute, c.second
+        )?;
+        Self::write_fraction(f, self.subsec_nanos())?;
+        write!(f, "Z")
+    }
+}
+
+impl Debug for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Timestamp({self})")
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = TimestampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { src: s.as_bytes(), pos: 0 };
+
+        let year = parser.digits(4, "expected a 4 digit year")?;
+        let extended = parser.eat(b'-');
+        let month = parser.digits(2, "expected a 2 digit month")? as u8;
+        if extended {
+            parser.expect(b'-', "expected '-' after the month")?;
+        }
+        let day_pos = parser.pos;
+        let day = parser.digits(2, "expected a 2 digit day")? as u8;
+        if !(1..=12).contains(&month) {
+            return Err(parser.invalid_at(day_pos - 2 - extended as usize, "month out of range"));
+        }
+        if day == 0 || day as u64 > TSComponents::days_in_month(year, month) {
+            return Err(parser.invalid_at(day_pos, "day out of range"));
+        }
+
+        let mut components = TSComponents::new(year, month, day);
+        let (mut nanos, mut offset) = (0, 0);
+        if !parser.done() {
+            if !(parser.eat(b'T') || parser.eat(b't') || parser.eat(b' ')) {
+                return Err(parser.invalid("expected 'T' between the date and the time"));
+            }
+            (components.hour, components.minute, components.second) = parser.time(extended)?;
+            nanos = parser.fraction()?;
+            offset = parser.offset()?;
+        }
+        if !parser.done() {
+            return Err(parser.invalid("unexpected trailing characters"));
+        }
+
+        let secs = components.as_timestamp() - offset;
+        Timestamp::from_nanos(secs as i128 * 1_000_000_000 + nanos as i128)
+            .ok_or(TimestampError::OutOfRange)
+    }
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn done(&self) -> bool {
+        self.pos == self.src.len()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.src.get(self.pos) == Some(&byte);
+        self.pos += found as usize;
+        found
+    }
+
+    fn expect(&mut self, byte: u8, reason: &'static str) -> Result<(), TimestampError> {
+        match self.eat(byte) {
+            true => Ok(()),
+            false => Err(self.invalid(reason)),
+        }
+    }
+
+    fn digits(&mut self, count: usize, reason: &'static str) -> Result<u32, TimestampError> {
+        let digits = self
+            .src
+            .get(self.pos..self.pos + count)
+            .filter(|digits| digits.iter().all(u8::is_ascii_digit))
+            .ok_or(self.invalid(reason))?;
+        self.pos += count;
+        Ok(digits.iter().fold(0, |value, digit| value * 10 + (digit - b'0') as u32))
+    }
+
+    /// `HH:MM[:SS]` or `HHMM[SS]`, the seconds default to 0
+    fn time(&mut self, extended: bool) -> Result<(u8, u8, u8), TimestampError> {
+        let start = self.pos;
+        let hour = self.digits(2, "expected a 2 digit hour")?;
+        if extended {
+            self.expect(b':', "expected ':' after the hour")?;
+        }
+        let minute = self.digits(2, "expected 2 digit minutes")?;
+        let has_seconds = match extended {
+            true => self.eat(b':'),
+            false => self.src.get(self.pos).is_some_and(u8::is_ascii_digit),
+        };
+        let second = match has_seconds {
+            true => self.digits(2, "expected 2 digit seconds")?,
+            false => 0,
+        };
+
+        if hour > 23 || minute > 59 || second > 59 {
+            return Err(self.invalid_at(start, "time out of range"));
+        }
+        Ok((hour as u8, minute as u8, second as u8))
+    }
+
+    /// Nanoseconds of an optional `.` or `,` separated fraction, digits past the 9th are ignored
+    fn fraction(&mut self) -> Result<u32, TimestampError> {
+        if !(self.eat(b'.') || self.eat(b',')) {
+            return Ok(0);
+        }
+
+        let start = self.pos;
+        let mut nanos = 0;
+        while let Some(digit) = self.src.get(self.pos).filter(|b| b.is_ascii_digit()) {
+            if self.pos - start < 9 {
+                nanos = nanos * 10 + (digit - b'0') as u32;
+            }
+            self.pos += 1;
+        }
+        match self.pos - start {
+            0 => Err(self.invalid("expected digits after the decimal point")),
+            len => Ok(nanos * 10u32.pow(9 - len.min(9) as u32)),
+        }
+    }
+
+    /// Seconds east of UTC of an optional `Z`, `±HH:MM`, `±HHMM` or `±HH`
+    fn offset(&mut self) -> Result<i64, TimestampError> {
+        if self.eat(b'Z') || self.eat(b'z') || self.done() {
+            return Ok(0);
+        }
+
+        let sign = match self.src[self.pos] {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return Err(self.invalid("expected 'Z' or an offset like '+03:00'")),
+        };
+        self.pos += 1;
+        let start = self.pos;
+        let hours = self.digits(2, "expected a 2 digit offset hour")?;
+        let has_minutes = self.eat(b':') || self.src.get(self.pos).is_some_and(u8::is_ascii_digit);
+        let minutes = match has_minutes {
+            true => self.digits(2, "expected 2 digit offset minutes")?,
+            false => 0,
+        };
+        if hours > 23 || minutes > 59 {
+            return Err(self.invalid_at(start, "offset out of range"));
+        }
+
+        Ok(sign * (hours as u64 * HOUR_IN_SECS + minutes as u64 * MINUTE_IN_SECS) as i64)
+    }
+
+    fn invalid(&self, reason: &'static str) -> TimestampError {
+        self.invalid_at(self.pos, reason)
+    }
+
+    fn invalid_at(&self, position: usize, reason: &'static str) -> TimestampError {
+        TimestampError::Invalid { position, reason }
+    }
+}
+
+impl Display for TimestampError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid { position, reason } => {
+                write!(f, "invalid timestamp at position {position}: {reason}")
+            }
+            Self::OutOfRange => write!(f, "timestamp out of range"),
+        }
+    }
+}
+
+impl Error for TimestampError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::{TimestampError, TimeZone};
+    use crate::time::timestamp::Timestamp;
+
+    fn parse(src: &str) -> Result<(i64, u32), TimestampError> {
+        src.parse::<Timestamp>().map(|ts| (ts.as_timestamp(), ts.subsec_nanos()))
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Timestamp::new(1_718_355_600).to_string(), "2024-06-14T09:00:00Z");
+        assert_eq!(Timestamp::new(-1).to_string(), "1969-12-31T23:59:59Z");
+        let ts = Timestamp::from_nanos(1_718_355_600_250_000_000).unwrap();
+        assert_eq!(ts.to_string(), "2024-06-14T09:00:00.250Z");
+        assert_eq!(format!("{ts:?}"), "Timestamp(2024-06-14T09:00:00.250Z)");
+        let ts = Timestamp::from_nanos(1_718_355_600_000_001_000).unwrap();
+        assert_eq!(ts.to_string(), "2024-06-14T09:00:00.000001Z");
+
+        let ts = Timestamp::new(1_718_355_600);
+        let tz = TimeZone::parse("UTC+3").unwrap();
+        assert_eq!(ts.to_rfc3339_in(&tz), "2024-06-14T12:00:00+03:00");
+        let tz = TimeZone::parse("UTC-05:30").unwrap();
+        assert_eq!(ts.to_rfc3339_in(&tz), "2024-06-14T03:30:00-05:30");
+        assert_eq!(ts.to_rfc3339_in(&TimeZone::utc()), "2024-06-14T09:00:00Z");
+    }
+
+    #[test]
+    fn test_format() {
+        let ts = Timestamp::new(1_717_535_109); // 2024-06-04T21:05:09Z
+        assert_eq!(ts.format("%a %e %b %Y, %I:%M %p"), "Tue  4 Jun 2024, 09:05 PM");
+        assert_eq!(
+            ts.format("%A %B %d (day %j) %F %T %R"),
+            "Tuesday June 04 (day 156) 2024-06-04 21:05:09 21:05"
+        );
+        assert_eq!(ts.format("%y %C %w %u %s %% %q"), "24 20 2 2 1717535109 % %q");
+        assert_eq!(Timestamp::new(1_735_516_800).format("%F %G-W%V-%u"), "2024-12-30 2025-W01-1");
+
+        let tz = TimeZone::parse("UTC+3").unwrap();
+        assert_eq!(ts.format_in("%F %T %z %:z %Z", &tz), "2024-06-05 00:05:09 +0300 +03:00 UTC+3");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("2024-06-14"), Ok((1_718_323_200, 0)));
+        assert_eq!(parse("2024-06-14T09:00:00Z"), Ok((1_718_355_600, 0)));
+        assert_eq!(parse("2024-06-14t09:00z"), Ok((1_718_355_600, 0)));
+        assert_eq!(parse("2024-06-14 12:00:00+03:00"), Ok((1_718_355_600, 0)));
+        assert_eq!(parse("2024-06-14T03:30-0530"), Ok((1_718_355_600, 0)));
+        assert_eq!(parse("20240614T120000+03"), Ok((1_718_355_600, 0)));
+        assert_eq!(parse("2024-06-14T09:00:00.25Z"), Ok((1_718_355_600, 250_000_000)));
+        assert_eq!(parse("2024-06-14T09:00:00,123456789123Z"), Ok((1_718_355_600, 123_456_789)));
+        assert_eq!(parse("1969-12-31T23:59:59.5Z"), Ok((-1, 500_000_000)));
+
+        let invalid = |position, reason| Err(TimestampError::Invalid { position, reason });
+        assert_eq!(parse("2024-02-30"), invalid(8, "day out of range"));
+        assert_eq!(parse("2024-13-01"), invalid(5, "month out of range"));
+        assert_eq!(parse("2024-06-14T24:00"), invalid(11, "time out of range"));
+        let offset_reason = "expected 'Z' or an offset like '+03:00'";
+        assert_eq!(parse("2024-06-14T09:00:00 UTC"), invalid(19, offset_reason));
+        assert_eq!(parse("2024-6-14"), invalid(5, "expected a 2 digit month"));
+        assert_eq!(parse("0000-01-01T00:00:00+01:00"), Err(TimestampError::OutOfRange));
+
+        let nanos = Timestamp::from_nanos(1_718_355_600_000_001_000).unwrap();
+        for ts in [Timestamp::new(-86_401), nanos] {
+            assert_eq!(ts.to_string().parse::<Timestamp>(), Ok(ts));
+        }
+    }
+}
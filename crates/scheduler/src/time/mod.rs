@@ -1,11 +1,33 @@
+pub use calendar::*;
+pub use clock::*;
 pub use consts::*;
+pub use cron::*;
+pub use exclusion::*;
+pub use format::*;
+pub use phrase::*;
+pub use solar::*;
 pub use timespan::*;
+pub use timezone::*;
 pub use weekday::*;
 
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
 use crate::time::timestamp::Timestamp;
+use crate::time::ts_components::TSComponents;
 
+mod calendar;
+mod clock;
 mod consts;
+mod cron;
+mod exclusion;
+mod format;
+mod phrase;
+#[cfg(feature = "serde")]
+pub(crate) mod serialize;
+mod solar;
 mod timespan;
+mod timezone;
 pub mod timestamp;
 mod weekday;
 
@@ -13,57 +35,252 @@ mod ts_components;
 mod ts_components_legacy;
 
 /// Time is represented in the 24hour clock e.g. 13:43
+#[derive(Clone, Copy)]
 pub(crate) struct Time {
     hours: u8,
     minutes: u8,
     seconds: Option<u8>,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) struct Date {
     year: u32,
     month: u8,
     day: u8,
 }
 
+/// Why a time of day couldn't be parsed, see [crate::time::TimeSpan::at]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeError {
+    /// Not a time of day such as `HH:MM`, `HH:MM:SS`, `3:45pm`, `noon` or `midnight`
+    Malformed,
+    /// Above 23, or outside of 1 to 12 in the 12-hour clock
+    Hour(u8),
+    Minute(u8),
+    Second(u8),
+}
+
 impl Time {
-    pub(crate) fn from_time_str(time_str: &str) -> Result<Time, ()> {
-        let [Some(hours), Some(minutes)] = time_str
+    /// Parses `HH:MM` or `HH:MM:SS` in the 24-hour clock, `H[:MM[:SS]]` followed by `am` or `pm`
+    /// (e.g. `3:45pm` or `10 AM`), `noon` or `midnight`
+    pub(crate) fn from_time_str(time_str: &str) -> Result<Time, TimeError> {
+        let time_str = time_str.trim().to_ascii_lowercase();
+        match time_str.as_str() {
+            "noon" => return Ok(Time { hours: 12, minutes: 0, seconds: None }),
+            "midnight" => return Ok(Time { hours: 0, minutes: 0, seconds: None }),
+            _ => {}
+        }
+
+        let (clock, pm) = match time_str.strip_suffix("am") {
+            Some(clock) => (clock.trim_end(), Some(false)),
+            None => match time_str.strip_suffix("pm") {
+                Some(clock) => (clock.trim_end(), Some(true)),
+                None => (time_str.as_str(), None),
+            },
+        };
+
+        // the hours may be a single digit, the minutes and seconds are always 2 digits
+        let parts = clock
             .split(':')
-            .map(|t: &str| t.parse::<u8>().ok())
-            .collect::<Vec<Option<u8>>>()[..]
-        else {
-            return Err(());
+            .enumerate()
+            .map(|(idx, part)| match part.len() {
+                1 if idx == 0 => part.parse::<u8>().ok(),
+                2 if part.bytes().all(|byte| byte.is_ascii_digit()) => part.parse::<u8>().ok(),
+                _ => None,
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or(TimeError::Malformed)?;
+        let time = match parts[..] {
+            [hours] if pm.is_some() => Time { hours, minutes: 0, seconds: None },
+            [hours, minutes] => Time { hours, minutes, seconds: None },
+            [hours, minutes, seconds] => Time { hours, minutes, seconds: Some(seconds) },
+            _ => return Err(TimeError::Malformed),
+        };
+
+        if time.hours > 23 {
+            return Err(TimeError::Hour(time.hours));
+        }
+        if time.minutes > 59 {
+            return Err(TimeError::Minute(time.minutes));
+        }
+        if let Some(seconds) = time.seconds.filter(|seconds| *seconds > 59) {
+            return Err(TimeError::Second(seconds));
+        }
+
+        match pm {
+            Some(pm) => time.in_12_hour_clock(pm),
+            None => Ok(time),
+        }
+    }
+
+    /// Reads the hours in the 12-hour clock, e.g. 12 is midnight before noon and noon after it
+    pub(crate) fn in_12_hour_clock(mut self, pm: bool) -> Result<Time, TimeError> {
+        self.hours = match self.hours {
+            1..=12 => self.hours % 12 + 12 * pm as u8,
+            hours => return Err(TimeError::Hour(hours)),
         };
+        Ok(self)
+    }
 
-        Ok(Time {
-            hours,
-            minutes,
-            seconds: None,
-        })
+    /// Seconds since midnight
+    pub(crate) fn as_secs(&self) -> u64 {
+        self.hours as u64 * HOUR_IN_SECS
+            + self.minutes as u64 * MINUTE_IN_SECS
+            + self.seconds.unwrap_or(0) as u64
     }
 }
 
-impl Date {
-    pub(crate) fn today() -> Self {
-        let mut today = Timestamp::now();
-        let components = today.get_components();
-        Self {
-            day: components.day,
-            month: components.month,
-            year: components.year,
+/// `HH:MM`, or `HH:MM:SS` when the seconds are given
+impl Display for Time {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.hours, self.minutes)?;
+        match self.seconds {
+            Some(seconds) => write!(f, ":{seconds:02}"),
+            None => Ok(()),
         }
     }
+}
+
+impl Display for TimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed => {
+                write!(f, "expected a time of day such as '10:00', '18:30:15', '3:45pm' or 'noon'")
+            }
+            Self::Hour(hours) => write!(f, "hour {hours} out of range"),
+            Self::Minute(minutes) => write!(f, "minute {minutes} out of range"),
+            Self::Second(seconds) => write!(f, "second {seconds} out of range"),
+        }
+    }
+}
+
+impl Error for TimeError {}
+
+impl Date {
+    pub(crate) fn new(year: u32, month: u8, day: u8) -> Self {
+        Self { year, month, day }
+    }
+
+    /// `None` unless `(year, month, day)` is a date of the calendar
+    pub(crate) fn checked(year: u32, month: u8, day: u8) -> Option<Self> {
+        let valid = (1..=12).contains(&month)
+            && day >= 1
+            && day as u64 <= TSComponents::days_in_month(year, month);
+        valid.then_some(Self::new(year, month, day))
+    }
+
+    /// Adds `months` carrying over into the following years. The day is clamped to the length of the
+    /// resulting month, e.g. January 31st plus one month is February 28th (or 29th)
+    pub(crate) fn add_months(self, months: u32) -> Self {
+        let total = self.year * 12 + self.month as u32 - 1 + months;
+        let (year, month) = (total / 12, (total % 12) as u8 + 1);
+        let day = self.day.min(TSComponents::days_in_month(year, month) as u8);
+        Self { year, month, day }
+    }
+
+    /// Adds `years`, February 29th becomes February 28th when the resulting year isn't a leap year
+    pub(crate) fn add_years(self, years: u32) -> Self {
+        self.add_months(years * 12)
+    }
+
+    pub(crate) fn add_days(self, days: u32) -> Self {
+        let midnight = TSComponents::from(self).as_timestamp() + (days as u64 * DAY_IN_SECS) as i64;
+        Self::from(&TSComponents::from(midnight))
+    }
+
+    /// 0 is Sunday, see [WeekDay]
+    pub(crate) fn day_of_week(&self) -> u8 {
+        Timestamp::new(TSComponents::from(*self).as_timestamp()).day_of_week()
+    }
+}
+
+impl From<&TSComponents> for Date {
+    fn from(components: &TSComponents) -> Self {
+        Self::new(components.year, components.month, components.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, Months, NaiveDate};
+
+    use crate::time::{Date, Time, TimeError};
 
-    pub(crate) fn incr_year(mut self, year: u32) -> Self {
-        self.year += year;
-        self
+    #[test]
+    fn test_add_months() {
+        let months = [0, 1, 2, 3, 5, 6, 11, 12, 13, 23, 24, 25, 48, 59, 120, 1200];
+        let mut date = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2101, 1, 1).unwrap();
+
+        while date < end {
+            for n in months {
+                let expected = date.checked_add_months(Months::new(n)).unwrap();
+                let actual = Date::new(date.year() as u32, date.month() as u8, date.day() as u8)
+                    .add_months(n);
+                assert_eq!(
+                    (actual.year, actual.month as u32, actual.day as u32),
+                    (expected.year() as u32, expected.month(), expected.day()),
+                    "{date} + {n} months"
+                );
+            }
+            date = date.succ_opt().unwrap();
+        }
     }
-    pub(crate) fn incr_month(mut self, month: u8) -> Self {
-        self.month += month;
-        self
+
+    #[test]
+    fn test_add_years() {
+        let cases = [
+            ((2024, 2, 29), 1, (2025, 2, 28)),
+            ((2024, 2, 29), 4, (2028, 2, 29)),
+            ((2096, 2, 29), 4, (2100, 2, 28)),
+            ((1996, 2, 29), 4, (2000, 2, 29)),
+            ((2023, 12, 31), 1, (2024, 12, 31)),
+            ((2023, 3, 15), 0, (2023, 3, 15)),
+        ];
+
+        for ((year, month, day), years, expected) in cases {
+            let actual = Date::new(year, month, day).add_years(years);
+            assert_eq!((actual.year, actual.month, actual.day), expected);
+        }
     }
-    pub(crate) fn incr_day(mut self, day: u8) -> Self {
-        self.day += day;
-        self
+
+    #[test]
+    fn test_from_time_str() {
+        let valid = [
+            ("10:00", "10:00"),
+            ("00:00", "00:00"),
+            ("23:59:59", "23:59:59"),
+            ("7:05", "07:05"),
+            ("3:45pm", "15:45"),
+            ("3:45 PM", "15:45"),
+            ("10am", "10:00"),
+            ("12am", "00:00"),
+            ("12:30pm", "12:30"),
+            ("11:59:30 pm", "23:59:30"),
+            ("noon", "12:00"),
+            (" Midnight ", "00:00"),
+        ];
+        for (time_str, expected) in valid {
+            let time = Time::from_time_str(time_str).map(|time| time.to_string());
+            assert_eq!(time, Ok(expected.to_string()));
+        }
+
+        let invalid = [
+            ("99:99", TimeError::Hour(99)),
+            ("24:00", TimeError::Hour(24)),
+            ("10:60", TimeError::Minute(60)),
+            ("10:00:60", TimeError::Second(60)),
+            ("13pm", TimeError::Hour(13)),
+            ("0am", TimeError::Hour(0)),
+            ("10", TimeError::Malformed),
+            ("10:5", TimeError::Malformed),
+            ("10:00:00:00", TimeError::Malformed),
+            ("-1:00", TimeError::Malformed),
+            ("ten", TimeError::Malformed),
+            ("", TimeError::Malformed),
+        ];
+        for (time_str, expected) in invalid {
+            assert_eq!(Time::from_time_str(time_str).err(), Some(expected), "{time_str}");
+        }
     }
 }
@@ -0,0 +1,603 @@
+// Schedules written in plain English, the same wording the Display impl of TimeSpan produces:
+//
+// schedule       span ("and" span)*
+// span           "every" every | "cron" <cron expression>
+// every          weekday | duration | [N] ("business day" | "month" | "year")
+// duration       [N] unit (("," | "and") N unit)*, e.g. "2 days and 6 hours"
+// unit           second, minute, hour, day or week, singular or plural
+//
+// followed by any of:
+//
+// "on the" 28th [", skipping short months" | ", rolling over short months"]
+// "on the" (last day | 3rd business day | 2nd tuesday | last friday) ["of" november]
+// "on" monday, after a number of weeks
+// "from the week of" 2024-06-10, after a number of weeks on a weekday
+// "at" 10:00, 18:30:15, 10am, 3:45 pm, noon or midnight
+// "starting in" duration
+//
+// Words are case insensitive, numbers are written as digits and weekdays and months can be
+// shortened to their first 3 letters.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::time::{
+    AsTimeSpan, Cron, CronError, DAY_IN_SECS, HOUR_IN_SECS, MINUTE_IN_SECS, MonthDay,
+    ShortMonthPolicy, Step, Time, TimeError, TimeSpan, WEEK_IN_SECS, WeekDay,
+};
+use crate::time::format::{MONTH_NAMES, WEEKDAY_NAMES};
+use crate::time::timestamp::Timestamp;
+use crate::time::ts_components::TSComponents;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeSpanError {
+    /// `position` is the byte offset of the first invalid word
+    Invalid { position: usize, reason: &'static str },
+    /// The time of day at `position` is invalid
+    Time { position: usize, error: TimeError },
+    /// The expression after `cron` is invalid, its positions are relative to the expression
+    Cron(CronError),
+}
+
+/// Parses a schedule written in English, e.g. from a config file
+/// ```
+/// use scheduler::Scheduler;
+/// use scheduler::time::TimeSpan;
+/// let mut scheduler = Scheduler::new();
+///
+/// let ts = "every monday at 10am and every 3 days at 3:45".parse::<TimeSpan>().unwrap();
+/// assert_eq!(ts.to_string(), "every Monday at 10:00 and every 3 days at 03:45");
+///
+/// scheduler
+///     .every(ts)
+///     .perform(|| println!("hello!"));
+/// ```
+impl FromStr for TimeSpan {
+    type Err = TimeSpanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+
+        let mut ts = parser.span()?;
+        while !parser.done() {
+            if !parser.eat("and") {
+                return Err(parser.invalid("expected 'and' before the next schedule"));
+            }
+            ts = ts.and(parser.span()?);
+        }
+
+        Ok(ts)
+    }
+}
+
+/// A whitespace separated word, commas are words of their own
+#[derive(Clone, Copy)]
+struct Word {
+    start: usize,
+    end: usize,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    words: Vec<Word>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        let mut words = Vec::new();
+        let mut start = None;
+        for (idx, c) in src.char_indices().chain([(src.len(), ' ')]) {
+            if c.is_whitespace() || c == ',' {
+                if let Some(start) = start.take() {
+                    words.push(Word { start, end: idx });
+                }
+                if c == ',' {
+                    words.push(Word { start: idx, end: idx + 1 });
+                }
+            } else if start.is_none() {
+                start = Some(idx);
+            }
+        }
+
+        Self { src, words, pos: 0 }
+    }
+
+    fn done(&self) -> bool {
+        self.pos == self.words.len()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&'a str> {
+        let word = self.words.get(self.pos + offset)?;
+        Some(&self.src[word.start..word.end])
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.peek_at(0)
+    }
+
+    fn is(&self, offset: usize, expected: &str) -> bool {
+        self.peek_at(offset).is_some_and(|word| word.eq_ignore_ascii_case(expected))
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        let found = self.is(0, expected);
+        self.pos += found as usize;
+        found
+    }
+
+    fn expect(&mut self, expected: &str, reason: &'static str) -> Result<(), TimeSpanError> {
+        match self.eat(expected) {
+            true => Ok(()),
+            false => Err(self.invalid(reason)),
+        }
+    }
+
+    /// `true` when the next words start another span of a union rather than continue this one
+    fn at_next_span(&self) -> bool {
+        self.is(0, "and") && (self.is(1, "every") || self.is(1, "cron"))
+    }
+
+    fn invalid(&self, reason: &'static str) -> TimeSpanError {
+        self.invalid_at(self.pos, reason)
+    }
+
+    fn invalid_at(&self, word: usize, reason: &'static str) -> TimeSpanError {
+        let position = self.words.get(word).map_or(self.src.len(), |word| word.start);
+        TimeSpanError::Invalid { position, reason }
+    }
+
+    fn span(&mut self) -> Result<TimeSpan, TimeSpanError> {
+        if self.eat("cron") {
+            let first = self.pos;
+            while !self.done() && !self.at_next_span() {
+                self.pos += 1;
+            }
+            if first == self.pos {
+                return Err(self.invalid("expected a cron expression after 'cron'"));
+            }
+            let expr = &self.src[self.words[first].start..self.words[self.pos - 1].end];
+            return expr.parse::<Cron>().map(TimeSpan::from).map_err(TimeSpanError::Cron);
+        }
+
+        self.expect("every", "expected 'every' or 'cron'")?;
+        let mut ts = match self.peek().and_then(weekday) {
+            Some(day) => {
+                self.pos += 1;
+                day.midnight()
+            }
+            None => self.every()?,
+        };
+        self.clauses(&mut ts)?;
+
+        Ok(ts)
+    }
+
+    /// The part after `every` when it isn't a weekday, e.g. `2 hours` or `month`
+    fn every(&mut self) -> Result<TimeSpan, TimeSpanError> {
+        let start = self.pos;
+        let n = self.number()?.unwrap_or(1);
+        if n == 0 {
+            return Err(self.invalid_at(start, "the interval must be greater than 0"));
+        }
+
+        if self.eat("business") {
+            self.unit(&["day", "days"])?;
+            return u32::try_from(n)
+                .map(|_| n.business_day())
+                .map_err(|_| self.invalid_at(start, "too many business days"));
+        }
+        if self.is_unit(&["month", "months"]) {
+            self.pos += 1;
+            return match u8::try_from(n) {
+                Ok(_) => Ok(n.month()),
+                Err(_) => Err(self.invalid_at(start, "at most 255 months")),
+            };
+        }
+        if self.is_unit(&["year", "years"]) {
+            self.pos += 1;
+            return u32::try_from(n)
+                .map(|_| n.year())
+                .map_err(|_| self.invalid_at(start, "too many years"));
+        }
+
+        self.pos = start;
+        Ok(self.duration(false)?.second())
+    }
+
+    /// Seconds of e.g. `2 days, 6 hours and 20 seconds`. Unless `numbered` is set the number of the
+    /// first unit may be left out, to read as `every hour`
+    fn duration(&mut self, numbered: bool) -> Result<u64, TimeSpanError> {
+        let mut total: u64 = 0;
+        loop {
+            let start = self.pos;
+            let n = match self.number()? {
+                Some(n) => n,
+                None if total == 0 && !numbered => 1,
+                None => return Err(self.invalid("expected a number")),
+            };
+            let unit = self.duration_unit()?;
+            total = n
+                .checked_mul(unit)
+                .and_then(|secs| total.checked_add(secs))
+                .ok_or(self.invalid_at(start, "the duration is too long"))?;
+
+            let next_is_number = self.peek_at(1).is_some_and(is_number);
+            if (self.is(0, ",") || self.is(0, "and")) && next_is_number {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+
+        match total {
+            0 => Err(self.invalid_at(self.pos - 1, "the interval must be greater than 0")),
+            total => Ok(total),
+        }
+    }
+
+    fn duration_unit(&mut self) -> Result<u64, TimeSpanError> {
+        const UNITS: [(&[&str], u64); 5] = [
+            (&["second", "seconds", "sec", "secs"], 1),
+            (&["minute", "minutes", "min", "mins"], MINUTE_IN_SECS),
+            (&["hour", "hours", "hr", "hrs"], HOUR_IN_SECS),
+            (&["day", "days"], DAY_IN_SECS),
+            (&["week", "weeks"], WEEK_IN_SECS),
+        ];
+
+        for (names, secs) in UNITS {
+            if self.is_unit(names) {
+                self.pos += 1;
+                return Ok(secs);
+            }
+        }
+        match self.is_unit(&["month", "months", "year", "years", "business"]) {
+            true => Err(self.invalid("only seconds, minutes, hours, days and weeks add up")),
+            false => Err(self.invalid("expected a unit of time, e.g. 'hours' or 'days'")),
+        }
+    }
+
+    fn is_unit(&self, names: &[&str]) -> bool {
+        names.iter().any(|name| self.is(0, name))
+    }
+
+    fn unit(&mut self, names: &[&str]) -> Result<(), TimeSpanError> {
+        if !self.is_unit(names) {
+            return Err(self.invalid("expected 'day' or 'days' after 'business'"));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// An optional number written in digits
+    fn number(&mut self) -> Result<Option<u64>, TimeSpanError> {
+        let Some(word) = self.peek().filter(|word| is_number(word)) else {
+            return Ok(None);
+        };
+        let n = word.parse::<u64>().map_err(|_| self.invalid("number too large"))?;
+        self.pos += 1;
+        Ok(Some(n))
+    }
+
+    /// `at`, `on the`, `on` a weekday, `starting in` and `from the week of`, in any order
+    fn clauses(&mut self, ts: &mut TimeSpan) -> Result<(), TimeSpanError> {
+        let (mut at, mut on, mut starting) = (false, false, false);
+        let mut anchor = None;
+        loop {
+            let start = self.pos;
+            if self.eat("at") {
+                if std::mem::replace(&mut at, true) {
+                    return Err(self.invalid_at(start, "the time of day is given twice"));
+                }
+                ts.start_at.replace(self.time()?);
+            } else if self.eat("on") {
+                if std::mem::replace(&mut on, true) {
+                    return Err(self.invalid_at(start, "the day of the month is given twice"));
+                }
+                let weeks = matches!(ts.step, Step::Second) && ts.interval % WEEK_IN_SECS == 0;
+                if let Some(day) = self.peek().and_then(weekday).filter(|_| weeks) {
+                    self.pos += 1;
+                    *ts = ts.clone().on(day).expect("the span is whole weeks");
+                    continue;
+                }
+                if !matches!(ts.step, Step::Month(_) | Step::Year(_)) {
+                    return Err(self.invalid_at(start, "'on the' only applies to months and years"));
+                }
+                self.expect("the", "expected 'the' after 'on'")?;
+                ts.month_day.replace(self.month_day()?);
+            } else if self.eat("starting") {
+                if std::mem::replace(&mut starting, true) {
+                    return Err(self.invalid_at(start, "the start is given twice"));
+                }
+                self.expect("in", "expected 'in' after 'starting'")?;
+                ts.start_in.replace(self.duration(true)?);
+            } else if self.eat("from") {
+                if anchor.is_some() {
+                    return Err(self.invalid_at(start, "the week is given twice"));
+                }
+                for word in ["the", "week", "of"] {
+                    self.expect(word, "expected 'from the week of' followed by a date")?;
+                }
+                anchor = Some((start, self.date()?));
+            } else {
+                break;
+            }
+        }
+
+        match anchor {
+            Some((_, date)) if matches!(ts.step, Step::Week { .. }) => {
+                *ts = ts.clone().anchored_at(date).expect("dates are parsed as timestamps");
+            }
+            Some((start, _)) => {
+                return Err(self.invalid_at(start, "only weeks on a weekday start from a week"));
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// The part after `on the`, e.g. `28th`, `last day` or `2nd tuesday of november`
+    fn month_day(&mut self) -> Result<MonthDay, TimeSpanError> {
+        let start = self.pos;
+        let nth = match self.eat("last") {
+            true => None,
+            false => Some(self.ordinal()?),
+        };
+
+        if let Some(day) = self.peek().and_then(weekday) {
+            self.pos += 1;
+            if nth.is_some_and(|nth| nth > 5) {
+                return Err(self.invalid_at(start, "a month has at most 5 of each weekday"));
+            }
+            let month = match self.eat("of") {
+                true => Some(self.month()?),
+                false => None,
+            };
+            return Ok(MonthDay::Weekday { day, nth, month });
+        }
+
+        let Some(nth) = nth else {
+            self.expect("day", "expected 'day' or a weekday after 'last'")?;
+            return Ok(MonthDay::Last);
+        };
+        if self.eat("business") {
+            self.unit(&["day"])?;
+            return match nth {
+                1..=23 => Ok(MonthDay::BusinessDay(nth)),
+                _ => Err(self.invalid_at(start, "a month has at most 23 business days")),
+            };
+        }
+        if nth > 31 {
+            return Err(self.invalid_at(start, "day of the month out of range"));
+        }
+
+        let policy = match self.is(0, ",") {
+            true if self.is(1, "skipping") => ShortMonthPolicy::Skip,
+            true if self.is(1, "rolling") => ShortMonthPolicy::Roll,
+            _ => return Ok(MonthDay::Day(nth, ShortMonthPolicy::Clamp)),
+        };
+        self.pos += 2;
+        if policy == ShortMonthPolicy::Roll {
+            self.expect("over", "expected 'rolling over short months'")?;
+        }
+        self.expect("short", "expected 'short months'")?;
+        self.expect("months", "expected 'short months'")?;
+
+        Ok(MonthDay::Day(nth, policy))
+    }
+
+    /// `1st`, `22nd`, `3` or `first` through `fifth`
+    fn ordinal(&mut self) -> Result<u8, TimeSpanError> {
+        const WORDS: [&str; 5] = ["first", "second", "third", "fourth", "fifth"];
+
+        let word = self.peek().unwrap_or_default().to_ascii_lowercase();
+        let nth = match WORDS.iter().position(|name| *name == word) {
+            Some(idx) => Some(idx as u8 + 1),
+            None => ["st", "nd", "rd", "th", ""]
+                .iter()
+                .find_map(|suffix| word.strip_suffix(suffix).filter(|digits| is_number(digits)))
+                .and_then(|digits| digits.parse::<u8>().ok()),
+        };
+
+        match nth {
+            Some(nth) if nth > 0 => {
+                self.pos += 1;
+                Ok(nth)
+            }
+            _ => Err(self.invalid("expected a day such as '28th', 'last day' or '2nd tuesday'")),
+        }
+    }
+
+    fn month(&mut self) -> Result<u8, TimeSpanError> {
+        let found = self
+            .peek()
+            .and_then(|word| MONTH_NAMES.iter().position(|name| named(name, word)));
+        match found {
+            Some(idx) => {
+                self.pos += 1;
+                Ok(idx as u8 + 1)
+            }
+            None => Err(self.invalid("expected the name of a month")),
+        }
+    }
+
+    /// A date such as `2024-06-10`
+    fn date(&mut self) -> Result<(u32, u8, u8), TimeSpanError> {
+        let date = self.peek().filter(|word| word.len() == 10);
+        let Some(date) = date.and_then(|word| word.parse::<Timestamp>().ok()) else {
+            return Err(self.invalid("expected a date such as '2024-06-10'"));
+        };
+        self.pos += 1;
+        let components = TSComponents::from(date.as_timestamp());
+        Ok((components.year, components.month, components.day))
+    }
+
+    /// The part after `at`, see [Time::from_time_str]. `am` and `pm` may be words of their own
+    fn time(&mut self) -> Result<Time, TimeSpanError> {
+        let position = self.words.get(self.pos).map_or(self.src.len(), |word| word.start);
+        let mut end = position;
+        for _ in 0..2 {
+            if self.done() || (end != position && !(self.is(0, "am") || self.is(0, "pm"))) {
+                break;
+            }
+            end = self.words[self.pos].end;
+            self.pos += 1;
+        }
+
+        Time::from_time_str(&self.src[position..end])
+            .map_err(|error| TimeSpanError::Time { position, error })
+    }
+}
+
+fn is_number(word: &str) -> bool {
+    !word.is_empty() && word.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// The full name, its first 3 letters or the plural of either, e.g. `monday`, `Mon` or `mondays`
+fn weekday(word: &str) -> Option<WeekDay> {
+    let word = word.strip_suffix(['s', 'S']).filter(|word| word.len() >= 3).unwrap_or(word);
+    WEEKDAY_NAMES.iter().position(|name| named(name, word)).map(|idx| WeekDay::ALL[idx])
+}
+
+/// `true` when `word` is `name` or its first 3 letters
+fn named(name: &str, word: &str) -> bool {
+    name.eq_ignore_ascii_case(word) || name[..3].eq_ignore_ascii_case(word)
+}
+
+impl Display for TimeSpanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid { position, reason } => {
+                write!(f, "invalid schedule at position {position}: {reason}")
+            }
+            Self::Time { position, error } => {
+                write!(f, "invalid time of day at position {position}: {error}")
+            }
+            Self::Cron(err) => write!(f, "invalid cron expression: {err}"),
+        }
+    }
+}
+
+impl Error for TimeSpanError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::{
+        AsTimeSpan, cron, ShortMonthPolicy, TimeError, TimeSpan, TimeSpanError, TimeZone,
+    };
+    use crate::time::timestamp::Timestamp;
+    use crate::time::WeekDay::{Friday, Monday, Thursday, Tuesday};
+
+    fn parse(s: &str) -> TimeSpan {
+        s.parse::<TimeSpan>().unwrap_or_else(|err| panic!("{s:?}: {err}"))
+    }
+
+    #[test]
+    fn test_parse() {
+        let cases = [
+            ("every 2 hours", 2.hour()),
+            ("every monday at 10am", Monday.at("10:00").unwrap()),
+            ("every 3 days at 3:45", 3.day().at("03:45").unwrap()),
+            ("every month on the 28th", 1.month().on_the(28).unwrap()),
+            ("Every Hour", 1.hour()),
+            ("every 1 hour, 30 minutes and 5 seconds", 1.hour() + 30.minute() + 5.second()),
+            ("every 2 days and 6 hours", 2.day() + 6.hour()),
+            ("every fri at 6:30 pm", Friday.at("18:30").unwrap()),
+            ("every tuesday at noon", Tuesday.at("12:00").unwrap()),
+            ("every 2 weeks at midnight", 14.day().at("00:00").unwrap()),
+            ("every day at 12am", 1.day().at("00:00").unwrap()),
+            ("every day at 23:59:30", 1.day().at("23:59:30").unwrap()),
+            ("every 3 business days", 3.business_day()),
+            ("every month on the 3rd business day", 1.month().on_business_day(3).unwrap()),
+            ("every month on the second tuesday", Tuesday.nth_of_month(2).unwrap()),
+            ("every month on the last fri", Friday.last_of_month()),
+            ("every year on the 4th thursday of nov", Thursday.nth_in(11, 4).unwrap()),
+            ("every 3 months on the last day at 23:00", 3.month().last_day().at("23:00").unwrap()),
+            (
+                "every month on the 31st, skipping short months",
+                1.month().on_the(31).unwrap().on_short_month(ShortMonthPolicy::Skip),
+            ),
+            ("every 2 weeks on monday at 10am", 2.week().on(Monday).unwrap().at("10:00").unwrap()),
+            (
+                "every 3 weeks on fri from the week of 2024-06-12",
+                3.week().on(Friday).unwrap().anchored_at((2024, 6, 10)).unwrap(),
+            ),
+            ("cron 0 9 * * MON-FRI", cron("0 9 * * 1-5").unwrap()),
+            (
+                "every monday at midnight and cron 0 9 * * * and every 2 hours",
+                Monday.midnight().and(cron("0 9 * * *").unwrap()).and(2.hour()),
+            ),
+        ];
+
+        for (s, expected) in cases {
+            assert_eq!(parse(s).to_string(), expected.to_string(), "{s:?}");
+        }
+
+        let mut ts = parse("every hour starting in 30 minutes");
+        assert_eq!(ts.start_in, Some(30 * 60));
+        let next = ts.next_after(&Timestamp::new(0), &TimeZone::utc());
+        assert_eq!(next.as_timestamp(), 30 * 60);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let spans = [
+            2.hour(),
+            1.day() + 6.hour() + 20.second(),
+            Monday.midnight().and(Friday.evening()),
+            3.day().at("03:45").unwrap(),
+            5.month().on_the(28).unwrap(),
+            1.month().on_the(31).unwrap().on_short_month(ShortMonthPolicy::Roll),
+            2.year().on_the(29).unwrap().at("10:00").unwrap(),
+            Tuesday.nth_of_month(2).unwrap().at("18:00").unwrap(),
+            Thursday.nth_in(11, 4).unwrap(),
+            1.month().on_business_day(5).unwrap().at("07:00").unwrap(),
+            cron("*/15 9-17 * * MON-FRI").unwrap().and(1.week()),
+            2.week().on(Tuesday).unwrap().anchored_at((2024, 6, 12)).unwrap().at("09:30").unwrap(),
+        ];
+
+        for ts in spans {
+            let description = ts.to_string();
+            assert_eq!(parse(&description).to_string(), description);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let invalid = |position, reason| Err(TimeSpanError::Invalid { position, reason });
+        let time = |position, error| Err(TimeSpanError::Time { position, error });
+        let no_sum = "only seconds, minutes, hours, days and weeks add up";
+        let no_last = "expected 'day' or a weekday after 'last'";
+        let no_anchor = "only weeks on a weekday start from a week";
+        let no_date = "expected a date such as '2024-06-10'";
+        let cases = [
+            ("", invalid(0, "expected 'every' or 'cron'")),
+            ("each hour", invalid(0, "expected 'every' or 'cron'")),
+            ("every 0 hours", invalid(6, "the interval must be greater than 0")),
+            ("every 2 fortnights", invalid(8, "expected a unit of time, e.g. 'hours' or 'days'")),
+            ("every 2 days and 1 month", invalid(19, no_sum)),
+            ("every day at 25:00", time(13, TimeError::Hour(25))),
+            ("every day at 13 pm", time(13, TimeError::Hour(13))),
+            ("every day at 10:60", time(13, TimeError::Minute(60))),
+            ("every day at", time(12, TimeError::Malformed)),
+            ("every day on the 3rd", invalid(10, "'on the' only applies to months and years")),
+            ("every month on the 32nd", invalid(19, "day of the month out of range")),
+            ("every month on the 6th monday", invalid(19, "a month has at most 5 of each weekday")),
+            ("every month on the last week", invalid(24, no_last)),
+            ("every year on the last monday of", invalid(32, "expected the name of a month")),
+            ("every hour at 10:00 at 11:00", invalid(20, "the time of day is given twice")),
+            ("every hour starting in hours", invalid(23, "expected a number")),
+            ("every hour or so", invalid(11, "expected 'and' before the next schedule")),
+            ("cron", invalid(4, "expected a cron expression after 'cron'")),
+            ("every day from the week of 2024-06-10", invalid(10, no_anchor)),
+            ("every 2 weeks on monday from the week of", invalid(40, no_date)),
+        ];
+
+        for (s, expected) in cases {
+            assert_eq!(s.parse::<TimeSpan>().map(|ts| ts.to_string()), expected, "{s:?}");
+        }
+        assert!(matches!("cron 0 9 * *".parse::<TimeSpan>(), Err(TimeSpanError::Cron(_))));
+    }
+}
@@ -0,0 +1,487 @@
+// Serde support behind the `serde` feature. Timestamps, times of day and dates are strings in the
+// formats the crate already prints and parses:
+//
+// Timestamp      "2024-06-10T09:30:00Z"   RFC 3339, see [Timestamp::from_str]
+// Time           "09:30" or "09:30:15"
+// Date           "2024-06-10"
+//
+// A TimeSpan is a map with the version of the format and the fields that are set, e.g. in JSON
+//
+// {"version": 1, "every": {"type": "week", "period": 2, "day": "monday", "anchor": "2024-06-10"},
+//  "at": "10:00", "except": [{"type": "window", "from": "22:00", "to": "06:00"}]}
+//
+// New fields and variants may be added to version 1 as long as older definitions still read the
+// same, anything else bumps the version. Every field is validated when it's read back, the same
+// way the builder methods and the parsers validate them.
+
+use std::fmt::Display;
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error;
+
+use crate::time::{
+    AsTimeSpan, Cron, Date, DAY_IN_SECS, Exclusion, ExclusionKind, HolidayCalendar, MonthDay,
+    ShortMonthPolicy, Solar, Step, Time, TimeSpan, WEEK_DAYS, WeekDay,
+};
+use crate::time::solar::{SolarEvent, SolarSpan};
+use crate::time::timestamp::Timestamp;
+use crate::time::ts_components::TSComponents;
+
+/// The version of the format that's written, definitions of this version or older can be read
+pub(crate) const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    version: u32,
+    #[serde(flatten)]
+    inner: T,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SpanRepr {
+    every: StepRepr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    at: Option<Time>,
+    /// Seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    starting_in: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on: Option<MonthDayRepr>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    except: Vec<ExclusionRepr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    holidays: Option<CalendarRepr>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StepRepr {
+    Duration { seconds: u64 },
+    Weekday { day: WeekDay },
+    BusinessDay { period: u32 },
+    Month { period: u8 },
+    Year { period: u32 },
+    /// `anchor` is the Monday the weeks are counted from
+    Week { period: u32, day: WeekDay, anchor: Date },
+    Cron { expression: String },
+    /// `offset` is in seconds, negative before the event
+    Solar { event: SolarEvent, latitude: f64, longitude: f64, offset: i64 },
+    Union { spans: Vec<SpanRepr> },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MonthDayRepr {
+    Day { day: u8, short_month: ShortMonthPolicy },
+    Last,
+    BusinessDay { nth: u8 },
+    /// The last `day` of the month when `nth` is missing
+    Weekday {
+        day: WeekDay,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nth: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        month: Option<u8>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ExclusionRepr {
+    Weekday { day: WeekDay },
+    Dates { from: Date, to: Date },
+    Window { from: Time, to: Time },
+}
+
+#[derive(Serialize, Deserialize)]
+struct CalendarRepr {
+    #[serde(default)]
+    dates: Vec<Date>,
+    /// `MM-DD`
+    #[serde(default)]
+    yearly: Vec<String>,
+}
+
+impl Serialize for TimeSpan {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Versioned { version: FORMAT_VERSION, inner: SpanRepr::from(self) }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeSpan {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let versioned = Versioned::<SpanRepr>::deserialize(deserializer)?;
+        check_version(versioned.version)?;
+        versioned.inner.into_span().map_err(D::Error::custom)
+    }
+}
+
+/// Fails on definitions written by a newer version of the crate
+pub(crate) fn check_version<E: Error>(version: u32) -> Result<(), E> {
+    match version {
+        1..=FORMAT_VERSION => Ok(()),
+        _ => Err(E::custom(format!("unsupported format version {version}"))),
+    }
+}
+
+impl From<&TimeSpan> for SpanRepr {
+    fn from(ts: &TimeSpan) -> Self {
+        let every = match ts.step {
+            Step::Second => StepRepr::Duration { seconds: ts.interval },
+            Step::Weekday(day) => StepRepr::Weekday { day },
+            Step::BusinessDay(period) => StepRepr::BusinessDay { period },
+            Step::Month(period) => StepRepr::Month { period },
+            Step::Year(period) => StepRepr::Year { period },
+            Step::Week { period, day, anchor } => {
+                let anchor = Date::from(&TSComponents::from(anchor * DAY_IN_SECS as i64));
+                StepRepr::Week { period, day, anchor }
+            }
+            Step::Cron(ref cron) => StepRepr::Cron { expression: cron.to_string() },
+            Step::Solar(ref solar) => StepRepr::Solar {
+                event: solar.event,
+                latitude: solar.solar.latitude(),
+                longitude: solar.solar.longitude(),
+                offset: solar.offset,
+            },
+            Step::Union(ref triggers) => StepRepr::Union {
+                spans: triggers.iter().map(|trigger| SpanRepr::from(&trigger.ts)).collect(),
+            },
+        };
+        let on = ts.month_day.map(|month_day| match month_day {
+            MonthDay::Day(day, short_month) => MonthDayRepr::Day { day, short_month },
+            MonthDay::Last => MonthDayRepr::Last,
+            MonthDay::BusinessDay(nth) => MonthDayRepr::BusinessDay { nth },
+            MonthDay::Weekday { day, nth, month } => MonthDayRepr::Weekday { day, nth, month },
+        });
+        let except = ts.exceptions.iter().map(|exclusion| match exclusion.0 {
+            ExclusionKind::Weekday(day) => ExclusionRepr::Weekday { day },
+            ExclusionKind::Dates(from, to) => ExclusionRepr::Dates { from, to },
+            ExclusionKind::Window(from, to) => ExclusionRepr::Window { from, to },
+        });
+        let holidays = ts.calendar.as_ref().map(|calendar| CalendarRepr {
+            dates: calendar.dates.iter().copied().collect(),
+            yearly: calendar.yearly.iter().map(|(m, d)| format!("{m:02}-{d:02}")).collect(),
+        });
+
+        Self {
+            every,
+            at: ts.start_at,
+            starting_in: ts.start_in,
+            on,
+            except: except.collect(),
+            holidays,
+        }
+    }
+}
+
+impl SpanRepr {
+    fn into_span(self) -> Result<TimeSpan, String> {
+        let mut ts = match self.every {
+            StepRepr::Duration { seconds } => positive(seconds)?.second(),
+            StepRepr::Weekday { day } => {
+                TimeSpan::new(WEEK_DAYS as u64 * DAY_IN_SECS, Step::Weekday(day))
+            }
+            StepRepr::BusinessDay { period } => {
+                TimeSpan::new(0, Step::BusinessDay(positive(period)?))
+            }
+            StepRepr::Month { period } => TimeSpan::new(0, Step::Month(positive(period)?)),
+            StepRepr::Year { period } => TimeSpan::new(0, Step::Year(positive(period)?)),
+            StepRepr::Week { period, day, anchor } => (positive(period)? as u64)
+                .week()
+                .on(day)
+                .and_then(|ts| ts.anchored_at((anchor.year, anchor.month, anchor.day)))
+                .map_err(|err| err.to_string())?,
+            StepRepr::Cron { expression } => {
+                TimeSpan::from(expression.parse::<Cron>().map_err(|err| err.to_string())?)
+            }
+            StepRepr::Solar { event, latitude, longitude, offset } => {
+                let solar = Solar::new(latitude, longitude)
+                    .ok_or_else(|| "the latitude and longitude must be finite".to_string())?;
+                let ts = TimeSpan::new(0, Step::Solar(SolarSpan { solar, event, offset: 0 }));
+                // the offset is checked the same way as when it's given in code
+                let window = offset.unsigned_abs().second();
+                match offset < 0 {
+                    true => ts.before(window),
+                    false => ts.after(window),
+                }
+                .map_err(|err| err.to_string())?
+            }
+            StepRepr::Union { spans } => {
+                if spans.len() < 2 {
+                    return Err("a union has at least two spans".to_string());
+                }
+                let mut spans = spans.into_iter().map(SpanRepr::into_span);
+                let first = spans.next().expect("unions have at least two spans")?;
+                spans.try_fold(first, |union, ts| Ok::<_, String>(union.and(ts?)))?
+            }
+        };
+
+        ts.start_at = self.at;
+        ts.start_in = self.starting_in;
+        if let Some(on) = self.on {
+            if !matches!(ts.step, Step::Month(_) | Step::Year(_)) {
+                return Err("'on' only applies to months and years".to_string());
+            }
+            ts.month_day = Some(on.into_month_day()?);
+        }
+        for exclusion in self.except {
+            ts.exceptions.push(match exclusion {
+                ExclusionRepr::Weekday { day } => Exclusion(ExclusionKind::Weekday(day)),
+                ExclusionRepr::Dates { from, to } => Exclusion::dates(
+                    (from.year, from.month, from.day),
+                    (to.year, to.month, to.day),
+                )
+                .map_err(|err| err.to_string())?,
+                ExclusionRepr::Window { from, to } => Exclusion(ExclusionKind::Window(from, to)),
+            });
+        }
+        if let Some(holidays) = self.holidays {
+            ts.calendar = Some(Arc::new(holidays.into_calendar()?));
+        }
+
+        Ok(ts)
+    }
+}
+
+impl MonthDayRepr {
+    fn into_month_day(self) -> Result<MonthDay, String> {
+        let month_day = match self {
+            Self::Day { day, short_month } if (1..=31).contains(&day) => {
+                MonthDay::Day(day, short_month)
+            }
+            Self::Day { day, .. } => return Err(format!("day of the month {day} out of range")),
+            Self::Last => MonthDay::Last,
+            Self::BusinessDay { nth: nth @ 1..=23 } => MonthDay::BusinessDay(nth),
+            Self::BusinessDay { nth } => return Err(format!("business day {nth} out of range")),
+            Self::Weekday { nth: Some(nth), .. } if !(1..=5).contains(&nth) => {
+                return Err(format!("a month has at most 5 of each weekday, found {nth}"));
+            }
+            Self::Weekday { month: Some(month), .. } if !(1..=12).contains(&month) => {
+                return Err(format!("month {month} out of range"));
+            }
+            Self::Weekday { day, nth, month } => MonthDay::Weekday { day, nth, month },
+        };
+        Ok(month_day)
+    }
+}
+
+impl CalendarRepr {
+    fn into_calendar(self) -> Result<HolidayCalendar, String> {
+        let mut calendar = HolidayCalendar::new();
+        calendar.dates.extend(self.dates);
+        for yearly in self.yearly {
+            // any leap year will do to check the day
+            let date = yearly
+                .split_once('-')
+                .and_then(|(month, day)| HolidayCalendar::parse_date("2000", month, day))
+                .filter(|_| yearly.len() == 5)
+                .ok_or_else(|| format!("invalid yearly holiday {yearly:?}, expected MM-DD"))?;
+            calendar.yearly.insert((date.month, date.day));
+        }
+        Ok(calendar)
+    }
+}
+
+fn positive<N: Default + PartialEq>(n: N) -> Result<N, String> {
+    match n == N::default() {
+        true => Err("the interval must be greater than 0".to_string()),
+        false => Ok(n),
+    }
+}
+
+/// Serializes `value` as its [Display] string
+fn serialize_str<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// RFC 3339, e.g. `2024-06-10T09:30:00Z`
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_str(self, serializer)
+    }
+}
+
+/// Any ISO 8601 date-time or date that [Timestamp::from_str] accepts
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(|err| D::Error::custom(format!("invalid timestamp {s:?}: {err}")))
+    }
+}
+
+impl Serialize for Time {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_str(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Time {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Time::from_time_str(&s)
+            .map_err(|err| D::Error::custom(format!("invalid time of day {s:?}: {err}")))
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let date = format!("{:04}-{:02}-{:02}", self.year, self.month, self.day);
+        serializer.serialize_str(&date)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let parts = s.splitn(3, '-').collect::<Vec<_>>();
+        let date = match parts[..] {
+            [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+                HolidayCalendar::parse_date(year, month, day)
+            }
+            _ => None,
+        };
+        date.ok_or_else(|| D::Error::custom(format!("invalid date {s:?}, expected YYYY-MM-DD")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::time::{
+        AsTimeSpan, cron, Exclusion, HolidayCalendar, ShortMonthPolicy, Solar, TimeSpan, TimeZone,
+    };
+    use crate::time::timestamp::Timestamp;
+    use crate::time::WeekDay::{Friday, Monday, Saturday, Thursday};
+
+    fn round_trip(ts: &TimeSpan) -> TimeSpan {
+        let json = serde_json::to_string(ts).unwrap();
+        serde_json::from_str(&json).unwrap_or_else(|err| panic!("{json}: {err}"))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let holidays = HolidayCalendar::parse_ics(
+            "BEGIN:VEVENT\nDTSTART;VALUE=DATE:20241225\nRRULE:FREQ=YEARLY\nEND:VEVENT",
+        )
+        .unwrap();
+        let spans = [
+            2.hour() + 30.minute(),
+            Monday.at("10:00").unwrap(),
+            3.day().at("03:45:10").unwrap(),
+            1.month().on_the(31).unwrap().on_short_month(ShortMonthPolicy::Roll),
+            1.month().last_day(),
+            Thursday.nth_in(11, 4).unwrap(),
+            Friday.last_of_month(),
+            2.business_day().with_holidays(holidays),
+            2.week().on(Monday).unwrap().anchored_at((2024, 6, 12)).unwrap().at("09:30").unwrap(),
+            cron("*/15 9-17 * * MON-FRI").unwrap(),
+            Solar::new(52.37, 4.9).unwrap().sunset().before(30.minute()).unwrap(),
+            Monday.midnight().and(1.year().on_the(29).unwrap()),
+            1.hour()
+                .except(Saturday)
+                .except(Exclusion::between("22:00", "06:00").unwrap())
+                .except(Exclusion::dates((2024, 12, 20), (2025, 1, 6)).unwrap()),
+        ];
+
+        let (after, utc) = ("2024-06-14T12:00:00Z".parse::<Timestamp>().unwrap(), TimeZone::utc());
+        for mut ts in spans {
+            let mut restored = round_trip(&ts);
+            assert_eq!(restored.to_string(), ts.to_string());
+            assert_eq!(serde_json::to_value(&restored).unwrap(), serde_json::to_value(&ts).unwrap());
+
+            let (mut next, mut next_restored) = (after, after);
+            for _ in 0..5 {
+                next = ts.next_run_on_after(&next, &utc);
+                next_restored = restored.next_run_on_after(&next_restored, &utc);
+                assert_eq!(next, next_restored, "{ts}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let ts = 2.week().on(Monday).unwrap().anchored_at((2024, 6, 12)).unwrap();
+        let ts = ts.at("10:00").unwrap();
+        let expected = json!({
+            "version": 1,
+            "every": {"type": "week", "period": 2, "day": "monday", "anchor": "2024-06-10"},
+            "at": "10:00",
+        });
+        assert_eq!(serde_json::to_value(&ts).unwrap(), expected);
+
+        let ts = 1.month().on_the(15).unwrap();
+        let ts = ts.except(Exclusion::between("22:00", "06:00").unwrap());
+        let expected = json!({
+            "version": 1,
+            "every": {"type": "month", "period": 1},
+            "on": {"type": "day", "day": 15, "short_month": "clamp"},
+            "except": [{"type": "window", "from": "22:00", "to": "06:00"}],
+        });
+        assert_eq!(serde_json::to_value(&ts).unwrap(), expected);
+
+        let ts = "2024-06-10T09:30:00Z".parse::<Timestamp>().unwrap();
+        assert_eq!(serde_json::to_value(ts).unwrap(), json!("2024-06-10T09:30:00Z"));
+        let date = serde_json::from_value::<Timestamp>(json!("2024-06-10")).unwrap();
+        assert_eq!(date.as_timestamp(), 1_717_977_600);
+    }
+
+    #[test]
+    fn test_validation() {
+        let every = |every| json!({"version": 1, "every": every});
+        let monthly = |field: &str, value| {
+            let mut span = every(json!({"type": "month", "period": 1}));
+            span[field] = value;
+            span
+        };
+        let hourly = |field: &str, value| {
+            let mut span = every(json!({"type": "duration", "seconds": 3600}));
+            span[field] = value;
+            span
+        };
+        let sunset = |offset: i64| {
+            let place = json!({"type": "solar", "event": "sunset", "latitude": 0, "longitude": 0});
+            let mut span = every(place);
+            span["every"]["offset"] = json!(offset);
+            span
+        };
+        let reversed = json!([{"type": "dates", "from": "2024-12-20", "to": "2024-12-01"}]);
+
+        let cases = [
+            (monthly("version", json!(2)), "unsupported format version 2"),
+            (json!({"every": {"type": "month", "period": 1}}), "missing field `version`"),
+            (
+                every(json!({"type": "duration", "seconds": 0})),
+                "the interval must be greater than 0",
+            ),
+            (every(json!({"type": "day"})), "unknown variant `day`"),
+            (every(json!({"type": "weekday", "day": "someday"})), "unknown variant `someday`"),
+            (every(json!({"type": "cron", "expression": "0 9 * *"})), "expected 5 or 6 fields"),
+            (every(json!({"type": "union", "spans": []})), "a union has at least two spans"),
+            (
+                every(json!({"type": "week", "period": 1, "day": "monday", "anchor": "2024-02-30"})),
+                "invalid date \"2024-02-30\", expected YYYY-MM-DD",
+            ),
+            (sunset(-86_400), "an event can't be moved by a day or more"),
+            (hourly("at", json!("25:00")), "invalid time of day \"25:00\": hour 25 out of range"),
+            (hourly("except", reversed), "2024-12-20 is after 2024-12-01"),
+            (hourly("on", json!({"type": "last"})), "'on' only applies to months and years"),
+            (
+                monthly("on", json!({"type": "day", "day": 32, "short_month": "clamp"})),
+                "day of the month 32 out of range",
+            ),
+            (
+                monthly("holidays", json!({"yearly": ["02-30"]})),
+                "invalid yearly holiday \"02-30\", expected MM-DD",
+            ),
+        ];
+        for (value, expected) in cases {
+            let error = serde_json::from_value::<TimeSpan>(value.clone())
+                .err()
+                .unwrap_or_else(|| panic!("{value} should be invalid"))
+                .to_string();
+            assert!(error.contains(expected), "{value}: {error:?} should contain {expected:?}");
+        }
+    }
+}
@@ -0,0 +1,292 @@
+// Sunrise, sunset and civil twilight are computed offline with the sunrise equation:
+// https://en.wikipedia.org/wiki/Sunrise_equation
+//
+// The results are within a minute or two of the published times, which is plenty for scheduling.
+// Days on which the event doesn't happen (polar day and night) are skipped.
+
+use std::fmt::{Display, Formatter};
+
+use crate::time::{DAY_IN_SECS, Step, TimeSpan};
+use crate::time::timestamp::Timestamp;
+
+/// The Julian day of 2000-01-01T12:00:00Z
+const J2000: f64 = 2_451_545.0;
+/// The Julian day of the unix epoch
+const JULIAN_EPOCH: f64 = 2_440_587.5;
+/// Days from the unix epoch to 2000-01-01
+const J2000_DAYS: i64 = 10_957;
+/// The tilt of the earth's axis
+const OBLIQUITY: f64 = 23.4397;
+/// Every event happens at least once a year anywhere on earth, even at the poles
+const MAX_SEARCH_DAYS: i64 = 2 * 366;
+
+/// A place on earth, its spans run relative to the sun there
+/// ```
+/// use scheduler::Scheduler;
+/// use scheduler::time::{AsTimeSpan, Solar};
+/// let mut scheduler = Scheduler::new();
+/// let amsterdam = Solar::new(52.37, 4.90).unwrap();
+///
+/// scheduler
+///     .every(amsterdam.sunset().before(30.minute()).unwrap())
+///     .perform(|| println!("lights on!"));
+///
+/// scheduler
+///     .every(amsterdam.dawn().and(amsterdam.dusk()))
+///     .perform(|| println!("switching the camera mode!"));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Solar {
+    /// Degrees north of the equator, negative in the south
+    latitude: f64,
+    /// Degrees east of Greenwich, negative in the west
+    longitude: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub(crate) enum SolarEvent {
+    Sunrise,
+    Sunset,
+    /// The morning civil twilight begins, the sun is 6° below the horizon
+    Dawn,
+    /// The evening civil twilight ends, the sun is 6° below the horizon
+    Dusk,
+}
+
+/// A [SolarEvent] at a place, offset by `offset` seconds
+#[derive(Clone, Copy)]
+pub(crate) struct SolarSpan {
+    pub(crate) solar: Solar,
+    pub(crate) event: SolarEvent,
+    pub(crate) offset: i64,
+}
+
+impl Solar {
+    /// The latitude is clamped to `-89.0..=89.0` and the longitude to `-180.0..=180.0`. Closer to
+    /// the poles the sun barely moves up or down in a day, too little to tell when it rises.
+    /// `None` if either of them is NaN or infinite
+    pub fn new(latitude: f64, longitude: f64) -> Option<Self> {
+        if !latitude.is_finite() || !longitude.is_finite() {
+            return None;
+        }
+        Some(Self {
+            latitude: latitude.clamp(-89.0, 89.0),
+            longitude: longitude.clamp(-180.0, 180.0),
+        })
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// Every day when the top of the sun appears on the horizon
+    pub fn sunrise(self) -> TimeSpan {
+        self.span(SolarEvent::Sunrise)
+    }
+
+    /// Every day when the top of the sun disappears below the horizon
+    pub fn sunset(self) -> TimeSpan {
+        self.span(SolarEvent::Sunset)
+    }
+
+    /// Every day when the morning civil twilight begins, i.e. when it gets light enough outside
+    pub fn dawn(self) -> TimeSpan {
+        self.span(SolarEvent::Dawn)
+    }
+
+    /// Every day when the evening civil twilight ends, i.e. when it gets dark outside
+    pub fn dusk(self) -> TimeSpan {
+        self.span(SolarEvent::Dusk)
+    }
+
+    fn span(self, event: SolarEvent) -> TimeSpan {
+        TimeSpan::new(0, Step::Solar(SolarSpan { solar: self, event, offset: 0 }))
+    }
+
+    /// The unix timestamp of `event` on the day that's `days` after the unix epoch, `None` if the
+    /// sun doesn't reach the altitude of the event that day
+    fn event_on(&self, days: i64, event: SolarEvent) -> Option<i64> {
+        let (sin, cos) = (|deg: f64| deg.to_radians().sin(), |deg: f64| deg.to_radians().cos());
+
+        // mean solar time, the mean anomaly and the equation of the center
+        let mean_time = (days - J2000_DAYS) as f64 - self.longitude / 360.0;
+        let anomaly = (357.5291 + 0.985_600_28 * mean_time).rem_euclid(360.0);
+        let center =
+            1.9148 * sin(anomaly) + 0.02 * sin(2.0 * anomaly) + 0.0003 * sin(3.0 * anomaly);
+        let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372).rem_euclid(360.0);
+        let transit =
+            J2000 + mean_time + 0.0053 * sin(anomaly) - 0.0069 * sin(2.0 * ecliptic_longitude);
+
+        let sin_declination = sin(ecliptic_longitude) * sin(OBLIQUITY);
+        let cos_declination = sin_declination.asin().cos();
+        let altitude: f64 = match event {
+            // the refraction of the atmosphere and the radius of the sun
+            SolarEvent::Sunrise | SolarEvent::Sunset => -0.833,
+            SolarEvent::Dawn | SolarEvent::Dusk => -6.0,
+        };
+        let cos_hour_angle = (sin(altitude) - sin(self.latitude) * sin_declination)
+            / (cos(self.latitude) * cos_declination);
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            return None;
+        }
+
+        let hour_angle = cos_hour_angle.acos().to_degrees();
+        let julian = match event {
+            SolarEvent::Sunrise | SolarEvent::Dawn => transit - hour_angle / 360.0,
+            SolarEvent::Sunset | SolarEvent::Dusk => transit + hour_angle / 360.0,
+        };
+        Some(((julian - JULIAN_EPOCH) * DAY_IN_SECS as f64).round() as i64)
+    }
+}
+
+impl SolarSpan {
+    /// The first offset event after `after`
+    pub(crate) fn next_after(&self, after: &Timestamp) -> Timestamp {
+        let day = DAY_IN_SECS as i64;
+        // the event of the previous day may still be ahead once it's offset
+        let first = after.as_timestamp().div_euclid(day) - 1 - (self.offset.abs() + day - 1) / day;
+
+        (first..first + MAX_SEARCH_DAYS)
+            .filter_map(|days| self.solar.event_on(days, self.event))
+            .map(|secs| secs + self.offset)
+            .find(|secs| *secs > after.as_timestamp())
+            .and_then(Timestamp::from_secs)
+            .expect("solar events happen at least once a year")
+    }
+}
+
+impl Display for SolarEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Sunrise => "sunrise",
+            Self::Sunset => "sunset",
+            Self::Dawn => "dawn",
+            Self::Dusk => "dusk",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// e.g. `52.37°N 4.9°E`
+impl Display for Solar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let north_south = if self.latitude < 0.0 { 'S' } else { 'N' };
+        let east_west = if self.longitude < 0.0 { 'W' } else { 'E' };
+        write!(
+            f,
+            "{}°{north_south} {}°{east_west}",
+            self.latitude.abs(),
+            self.longitude.abs()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::{AsTimeSpan, Solar, SpanError, TimeSpan, TimeZone};
+    use crate::time::timestamp::Timestamp;
+
+    fn next(ts: &mut TimeSpan, after: &str) -> Timestamp {
+        ts.next_after(&after.parse().unwrap(), &TimeZone::utc())
+    }
+
+    fn assert_near(actual: Timestamp, expected: &str) {
+        let expected = expected.parse::<Timestamp>().unwrap();
+        let diff = (actual.as_timestamp() - expected.as_timestamp()).abs();
+        assert!(diff <= 120, "{actual} is more than 2 minutes from {expected}");
+    }
+
+    #[test]
+    fn test_events() {
+        let amsterdam = Solar::new(52.37, 4.90).unwrap();
+        let after = "2024-06-21T00:00:00Z";
+        assert_near(next(&mut amsterdam.dawn(), after), "2024-06-21T02:28:00Z");
+        assert_near(next(&mut amsterdam.sunrise(), after), "2024-06-21T03:18:00Z");
+        assert_near(next(&mut amsterdam.sunset(), after), "2024-06-21T20:06:00Z");
+        assert_near(next(&mut amsterdam.dusk(), after), "2024-06-21T20:56:00Z");
+
+        // a day later once the event of the day has passed
+        let after = "2024-06-21T21:00:00Z";
+        assert_near(next(&mut amsterdam.sunset(), after), "2024-06-22T20:06:00Z");
+
+        let new_york = Solar::new(40.71, -74.01).unwrap();
+        let after = "2024-12-21T05:00:00Z";
+        assert_near(next(&mut new_york.sunrise(), after), "2024-12-21T12:17:00Z");
+        assert_near(next(&mut new_york.sunset(), after), "2024-12-21T21:32:00Z");
+
+        let sydney = Solar::new(-33.87, 151.21).unwrap();
+        assert_near(next(&mut sydney.sunrise(), "2024-12-20T12:00:00Z"), "2024-12-20T18:41:00Z");
+    }
+
+    #[test]
+    fn test_offset() {
+        let amsterdam = Solar::new(52.37, 4.90).unwrap();
+        let after = "2024-06-21T00:00:00Z";
+        let mut before = amsterdam.sunset().before(30.minute()).unwrap();
+        assert_near(next(&mut before, after), "2024-06-21T19:36:00Z");
+        let mut later = amsterdam.sunrise().after(1.hour()).unwrap();
+        assert_near(next(&mut later, after), "2024-06-21T04:18:00Z");
+
+        // the sunset of the day has passed but the offset run hasn't yet
+        let mut before = amsterdam.sunset().before(2.hour()).unwrap();
+        assert_near(next(&mut before, "2024-06-22T17:00:00Z"), "2024-06-22T18:06:00Z");
+        let mut later = amsterdam.sunset().after(5.hour()).unwrap();
+        assert_near(next(&mut later, "2024-06-22T00:30:00Z"), "2024-06-22T01:06:00Z");
+
+        // only plain durations are offsets
+        let not_a_duration = Some(SpanError::NotADuration);
+        assert_eq!(amsterdam.sunset().before(1.month()).err(), not_a_duration);
+        assert_eq!(amsterdam.sunset().after(2.business_day()).err(), not_a_duration);
+        let at_ten = 1.day().at("10:00").unwrap();
+        assert_eq!(amsterdam.sunset().before(at_ten).err(), not_a_duration);
+
+        // offsets stay within a day of the event
+        let offset = Some(SpanError::Offset);
+        assert_eq!(amsterdam.sunset().before(800.day()).err(), offset);
+        assert_eq!(amsterdam.sunset().after(u64::MAX.second()).err(), offset);
+        assert_eq!(amsterdam.sunset().after(12.hour()).unwrap().after(12.hour()).err(), offset);
+        let mut latest = amsterdam.sunset().after(86_399.second()).unwrap();
+        assert_near(next(&mut latest, "2024-06-22T20:00:00Z"), "2024-06-22T20:06:00Z");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(Solar::new(f64::NAN, 4.90), None);
+        assert_eq!(Solar::new(52.37, f64::INFINITY), None);
+        assert_eq!(Solar::new(95.0, -200.0).map(|solar| solar.latitude()), Some(89.0));
+    }
+
+    #[test]
+    fn test_polar() {
+        // the sun doesn't set in Tromsø from late May until late July
+        let tromso = Solar::new(69.65, 18.96).unwrap();
+        let sunset = next(&mut tromso.sunset(), "2024-06-15T00:00:00Z");
+        assert_eq!(sunset.format("%m"), "07");
+        assert!(sunset > "2024-07-20T00:00:00Z".parse().unwrap());
+
+        // the sun only skims the horizon around the equinoxes this close to the pole
+        let mut pole = Solar::new(90.0, 0.0).unwrap().sunrise();
+        let sunrise = next(&mut pole, "2024-06-01T00:00:00Z");
+        assert_eq!(sunrise.format("%Y-%m"), "2024-09");
+    }
+
+    #[test]
+    fn test_display() {
+        let amsterdam = Solar::new(52.37, 4.9).unwrap();
+        assert_eq!(amsterdam.sunset().to_string(), "every sunset at 52.37°N 4.9°E");
+        assert_eq!(
+            amsterdam.sunset().before(30.minute()).unwrap().to_string(),
+            "30 minutes before every sunset at 52.37°N 4.9°E"
+        );
+        assert_eq!(
+            Solar::new(-33.87, -70.5).unwrap().dawn().after(1.hour()).unwrap().to_string(),
+            "1 hour after every dawn at 33.87°S 70.5°W"
+        );
+    }
+}
@@ -1,17 +1,49 @@
-use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::ops::{Add, Mul};
 use std::time::Duration;
 
-use crate::time::{DAY_IN_SECS, HOUR_IN_SECS, MINUTE_IN_SECS, Time, WEEK_IN_SECS};
+use std::sync::Arc;
+
+use crate::time::{
+    Cron, Date, DAY_IN_SECS, Exclusion, HolidayCalendar, HOUR_IN_SECS, MINUTE_IN_SECS, Time,
+    TimeError, TimeZone, WEEK_DAYS, WEEK_IN_SECS, WeekDay,
+};
+use crate::time::format::MONTH_NAMES;
+use crate::time::solar::SolarSpan;
 use crate::time::timestamp::Timestamp;
+use crate::time::ts_components::TSComponents;
 
+#[derive(Clone)]
 pub(crate) enum Step {
     Year(u32),
     Month(u8),
-    Weekday,
+    Weekday(WeekDay),
     Second,
+    /// Every N business days, see [HolidayCalendar]
+    BusinessDay(u32),
+    Cron(Cron),
+    /// Fires whenever any of its members does, see [TimeSpan::and]
+    Union(Vec<Trigger>),
+    /// Sunrise, sunset or twilight at a place, see [crate::time::Solar]
+    Solar(SolarSpan),
+    /// `day` of every `period`-th week counted from the week that starts on the Monday `anchor`
+    /// days after the unix epoch, see [TimeSpan::on]
+    Week { period: u32, day: WeekDay, anchor: i64 },
 }
 
+/// 1969-12-29, the Monday of the first ISO week of 1970. Multi-week spans count their weeks from
+/// here unless they're anchored somewhere else
+const WEEK_ANCHOR: i64 = -3;
+
+/// A member of a [Step::Union] along with the next instant it fires at
+#[derive(Clone)]
+pub(crate) struct Trigger {
+    pub(crate) ts: TimeSpan,
+    next: Option<Timestamp>,
+}
+
+#[derive(Clone)]
 pub struct TimeSpan {
     /// Interval represents the time in seconds that needs to elapse before the event occurs
     pub(crate) interval: u64,
@@ -20,91 +52,705 @@ pub struct TimeSpan {
     /// start_in specifies how much time in seconds needs to elapse, before its scheduled execution interval
     pub(crate) start_in: Option<u64>,
     pub(crate) step: Step,
+    /// month_day pins `.month()` and `.year()` spans to a day of the month
+    pub(crate) month_day: Option<MonthDay>,
+    /// exceptions holds the weekdays, dates and times of day the span never runs on
+    pub(crate) exceptions: Vec<Exclusion>,
+    /// calendar holds the holidays that business day spans skip, only weekends are skipped without it
+    pub(crate) calendar: Option<Arc<HolidayCalendar>>,
+}
+
+/// What an `on_the` schedule does in months that are too short for its day, e.g. the 31st in April
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ShortMonthPolicy {
+    /// Run on the last day of the month instead
+    Clamp,
+    /// Don't run that month, wait for the next period
+    Skip,
+    /// Run as many days into the next month as the day overflows, e.g. April 31st runs on May 1st
+    Roll,
+}
+
+/// Why a span can't be built, e.g. a day of the month that doesn't exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanError {
+    /// Outside of 1 to 31
+    MonthDay(u8),
+    /// Outside of 1 to 23, the most business days a month can have
+    BusinessDay(u8),
+    /// Outside of 1 to 5, the most times a weekday occurs in a month
+    Nth(u8),
+    /// Outside of 1 to 12
+    Month(u8),
+    /// A year, month and day that isn't a date
+    Date(u32, u8, u8),
+    /// A range of dates whose first date is after its last one
+    DateRange((u32, u8, u8), (u32, u8, u8)),
+    /// The span isn't a plain duration such as `30.minute()`, see [crate::Schedule::with_jitter]
+    NotADuration,
+    /// A sunrise, sunset or twilight moved by a day or more, see [TimeSpan::before]
+    Offset,
+    /// The span isn't whole weeks on a weekday, see [TimeSpan::on]
+    NotWeeks,
+    /// An ISO 8601 week that the year doesn't have
+    Week(u8),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum MonthDay {
+    Day(u8, ShortMonthPolicy),
+    Last,
+    /// The Nth business day of the month, months with less business days are skipped
+    BusinessDay(u8),
+    /// The Nth `day` of the month or the last one if `nth` is `None`. Months without an Nth `day` are
+    /// skipped, `month` pins yearly spans to a month of the year
+    Weekday { day: WeekDay, nth: Option<u8>, month: Option<u8> },
 }
 
 impl TimeSpan {
-    pub(crate) fn next_run_on(&mut self) -> Timestamp {
+    pub(crate) fn new(interval: u64, step: Step) -> Self {
+        Self {
+            interval,
+            start_at: None,
+            start_in: None,
+            step,
+            month_day: None,
+            exceptions: Vec::new(),
+            calendar: None,
+        }
+    }
+
+    /// The first run at or after `from`, plain durations start right at `from`
+    pub(crate) fn first_run_from(&mut self, from: &Timestamp, tz: &TimeZone) -> Timestamp {
+        let next = match self.is_duration() {
+            true => *from,
+            false => self.next_after(&from.saturating_add_secs(-1), tz),
+        };
+        self.skip_excluded(next, tz)
+    }
+
+    /// Computes the first run after `after`. Times of day are wall-clock times in `tz`, plain durations
+    /// are added to `after` regardless of any DST transition in between
+    pub(crate) fn next_after(&mut self, after: &Timestamp, tz: &TimeZone) -> Timestamp {
         let start_in = self.start_in.take();
-        let mut next_timestamp = Timestamp::now();
+        if let Step::Union(triggers) = &mut self.step {
+            return Self::next_union_run_on(triggers, after, tz);
+        }
+        let mut next_timestamp = tz.to_local(after);
         match &self.step {
+            Step::Solar(solar) => return solar.next_after(after),
+            Step::Cron(cron) => {
+                // cron expressions are validated on parsing, so they always have an upcoming occurrence
+                return cron
+                    .next_after(after, tz)
+                    .expect("cron expression has no upcoming occurrence");
+            }
+            Step::Weekday(day) => {
+                let until = (*day as u8 + WEEK_DAYS - next_timestamp.day_of_week()) % WEEK_DAYS;
+                next_timestamp += Duration::from_secs(until as u64 * DAY_IN_SECS);
+                if let Some(ref start_at) = self.start_at {
+                    next_timestamp.set_time(start_at);
+                }
+                // it's the same day of the week but the time has already passed
+                if tz.to_utc(&next_timestamp) <= *after {
+                    next_timestamp += Duration::from_secs(WEEK_IN_SECS);
+                }
+                return tz.to_utc(&next_timestamp);
+            }
+            Step::BusinessDay(period) => {
+                return tz.to_utc(&self.next_business_day(&next_timestamp, *period));
+            }
+            Step::Week { period, day, anchor } => {
+                let period = (*period).max(1) as i64;
+                let days = next_timestamp.as_timestamp().div_euclid(DAY_IN_SECS as i64);
+                let monday = days - TSComponents::iso_weekday_of(days) as i64 + 1;
+                let skip = (period - (monday - anchor).div_euclid(7).rem_euclid(period)) % period;
+                let day = monday + skip * 7 + day.iso_index() as i64 - 1;
+                next_timestamp = Timestamp::new(day * DAY_IN_SECS as i64);
+                if let Some(ref start_at) = self.start_at {
+                    next_timestamp.set_time(start_at);
+                }
+                // the day of this week has already passed
+                if tz.to_utc(&next_timestamp) <= *after {
+                    next_timestamp += Duration::from_secs(period as u64 * WEEK_IN_SECS);
+                }
+                return tz.to_utc(&next_timestamp);
+            }
+            Step::Month(period) if self.month_day.is_some() => {
+                return tz.to_utc(&self.next_month_day(&next_timestamp, *period as u32));
+            }
+            Step::Year(period) if self.month_day.is_some() => {
+                return tz.to_utc(&self.next_month_day(&next_timestamp, *period * 12));
+            }
             Step::Month(period) => {
                 next_timestamp += Duration::from_secs(next_timestamp.get_month_interval(*period));
             }
             Step::Year(period) => {
                 next_timestamp += Duration::from_secs(next_timestamp.get_year_interval(*period));
             }
-            _ => {
-                next_timestamp += Duration::from_secs(start_in.unwrap_or(self.interval));
+            Step::Union(_) => unreachable!("unions are handled above"),
+            Step::Second => {
+                let next_timestamp = *after + Duration::from_secs(start_in.unwrap_or(self.interval));
+                let Some(ref start_at) = self.start_at else {
+                    return next_timestamp;
+                };
+                let mut next_timestamp = tz.to_local(&next_timestamp);
+                next_timestamp.set_time(start_at);
+                return tz.to_utc(&next_timestamp);
             }
         };
         if let Some(ref start_at) = self.start_at {
             next_timestamp.set_time(start_at);
         }
 
-        next_timestamp
+        tz.to_utc(&next_timestamp)
+    }
+
+    /// Returns the earliest upcoming instant across all members. Only the members that have fired since
+    /// they were last computed move on to their next instant, the rest keep theirs
+    fn next_union_run_on(triggers: &mut [Trigger], after: &Timestamp, tz: &TimeZone) -> Timestamp {
+        triggers
+            .iter_mut()
+            .map(|trigger| match trigger.next {
+                Some(next) if next > *after => next,
+                _ => *trigger.next.insert(trigger.ts.next_run_on_after(after, tz)),
+            })
+            .min()
+            .expect("unions have at least two members")
+    }
+
+    /// Same as [TimeSpan::next_after] but skips the excluded runs
+    pub(crate) fn next_run_on_after(&mut self, after: &Timestamp, tz: &TimeZone) -> Timestamp {
+        let next = self.next_after(after, tz);
+        self.skip_excluded(next, tz)
+    }
+
+    /// Moves `next` forward until it's no longer excluded. Plain durations keep their phase, e.g. an
+    /// hourly span at :17 stays at :17 after a blackout window
+    pub(crate) fn skip_excluded(&mut self, mut next: Timestamp, tz: &TimeZone) -> Timestamp {
+        // excluding every single day would otherwise loop forever, give up after ~27 years worth of days
+        const MAX_SKIPS: usize = 10_000;
+
+        for _ in 0..MAX_SKIPS {
+            let local = tz.to_local(&next);
+            let Some(until) = self.exceptions.iter().filter_map(|e| e.excluded_until(&local)).max()
+            else {
+                break;
+            };
+
+            let until = tz.to_utc(&until);
+            next = match self.is_duration() && self.interval > 0 {
+                true => {
+                    // a blackout that ends within a repeated hour can end before `next` in UTC
+                    let gap = (until.as_timestamp() - next.as_timestamp()).max(1) as u64;
+                    next + Duration::from_secs(gap.div_ceil(self.interval) * self.interval)
+                }
+                false => self.next_after(&until.saturating_add_secs(-1), tz),
+            };
+        }
+
+        next
     }
 
-    // TODO: this needs refactoring. We need to take more things into account instead of just incrementing the self.interval by "span"
-    pub fn and(mut self, span: TimeSpan) -> Self {
-        self.interval += span.interval;
+    /// Skips the runs that fall on excluded weekdays, dates or times of day
+    /// ```
+    /// use scheduler::Scheduler;
+    /// use scheduler::time::{AsTimeSpan, Exclusion, WeekDay::{Saturday, Sunday}};
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// scheduler
+    ///     .every(
+    ///         1.hour()
+    ///             .except(Saturday)
+    ///             .except(Sunday)
+    ///             .except(Exclusion::between("22:00", "06:00").unwrap())
+    ///             .except(Exclusion::dates((2024, 12, 20), (2025, 1, 6)).unwrap()),
+    ///     )
+    ///     .perform(|| println!("hourly during office hours!"));
+    /// ```
+    pub fn except<E: Into<Exclusion>>(mut self, exclusion: E) -> Self {
+        self.exceptions.push(exclusion.into());
         self
     }
 
-    // TODO: we have the same timezone problem as with the .midnight etc methods on Weekday
+    /// `true` for spans that are nothing but an amount of seconds, e.g. `2.hour()` or `1.day() + 6.hour()`
+    pub(crate) fn is_duration(&self) -> bool {
+        matches!(self.step, Step::Second)
+            && self.start_at.is_none()
+            && self.start_in.is_none()
+            && self.month_day.is_none()
+    }
+
+    /// The seconds of a plain duration, see [TimeSpan::is_duration]
+    pub(crate) fn duration_secs(&self) -> Result<u64, SpanError> {
+        match self.is_duration() {
+            true => Ok(self.interval),
+            false => Err(SpanError::NotADuration),
+        }
+    }
+
+    /// Combines two spans into a single one that fires whenever either of them does
+    /// ```
+    /// use scheduler::Scheduler;
+    /// use scheduler::time::WeekDay::{Friday, Monday};
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// scheduler
+    ///     .every(Monday.at("10:00").unwrap().and(Friday.at("15:00").unwrap()))
+    ///     .perform(|| println!("Monday morning and Friday afternoon!"));
+    /// ```
+    /// Call it after configuring each member, since `at`, `on_the` etc. only apply to the span they're
+    /// called on. To make a plain duration longer use `+` instead, e.g. `2.day() + 6.hour()`
+    pub fn and(self, span: TimeSpan) -> Self {
+        let mut triggers = match self.step {
+            Step::Union(triggers) => triggers,
+            _ => vec![Trigger::new(self)],
+        };
+        match span.step {
+            Step::Union(others) => triggers.extend(others),
+            _ => triggers.push(Trigger::new(span)),
+        }
+
+        TimeSpan::new(0, Step::Union(triggers))
+    }
+
+    /// The time is the wall-clock time in the timezone of the [crate::Scheduler], either `HH:MM` or
+    /// `HH:MM:SS` in the 24-hour clock, a 12-hour clock time such as `3:45pm`, `noon` or
+    /// `midnight`.
     /// `at` is usually called as:
     /// ```
-    /// use scheduler::{Scheduler, time::AsTimeSpan};
+    /// use scheduler::{Scheduler, time::{AsTimeSpan, TimeError}};
     /// let mut scheduler = Scheduler::with_tz("UTC+3").unwrap();
     ///
     /// scheduler
-    ///     .every(1.day().at("10:00")?.am().unwrap())
+    ///     .every(1.day().at("10:00")?)
     ///     .perform(|| println!("hello!"));
+    ///
+    /// assert_eq!(1.day().at("25:00").err(), Some(TimeError::Hour(25)));
+    /// # Ok::<(), TimeError>(())
     /// ```
-    pub fn at(mut self, time_str: &'static str) -> Result<Self, ()> {
-        // Default is pm, call .am()
-        let time_obj = match Time::from_time_str(time_str) {
-            Ok(t) => t,
-            Err(msg) => return Err(msg),
-        };
-        self.start_at.replace(time_obj);
+    pub fn at(mut self, time_str: &str) -> Result<Self, TimeError> {
+        self.start_at.replace(Time::from_time_str(time_str)?);
         Ok(self)
     }
 
-    // TODO: major refactoring here.
-    // We need to take into account timezones and the fact that the SystemTime::now() call returns a timestamp referring to the timezone of the computer, not GMT
-    // Everything should, by default, be GMT time and when timezones are implemented, then we can go from there
-    pub fn am(mut self) -> Result<Self, ()> {
-        if let Some(ref mut time) = self.start_at.as_mut() {
-            match (time.hours + 12).cmp(&24) {
-                Ordering::Equal => {
-                    time.hours = 0;
-                }
-                Ordering::Less => {
-                    time.hours += 12;
-                }
-                Ordering::Greater => return Err(()),
-            }
+    /// Reads the time given to [TimeSpan::at] in the 12-hour clock as before noon, e.g.
+    /// `at("12:30")?.am()` runs at 00:30. Fails for hours that aren't 1 to 12
+    pub fn am(self) -> Result<Self, TimeError> {
+        self.in_12_hour_clock(false)
+    }
+
+    /// Reads the time given to [TimeSpan::at] in the 12-hour clock as after noon, e.g.
+    /// `at("3:45")?.pm()` runs at 15:45. Fails for hours that aren't 1 to 12
+    pub fn pm(self) -> Result<Self, TimeError> {
+        self.in_12_hour_clock(true)
+    }
+
+    fn in_12_hour_clock(mut self, pm: bool) -> Result<Self, TimeError> {
+        if let Some(time) = self.start_at {
+            self.start_at.replace(time.in_12_hour_clock(pm)?);
         }
-        return Ok(self);
+        Ok(self)
     }
 
+    /// Pins a `.month()` or `.year()` span to a day of the month, at midnight unless `.at()` is called.
+    /// The schedule first runs on that day of the current month if it hasn't passed yet and then every
+    /// period. Fails for days that aren't 1 to 31, months that are too short run on their last day,
+    /// see [TimeSpan::on_short_month] to change that.
+    ///
     /// # Examples:
     /// ```
     /// use scheduler::{Scheduler, time::AsTimeSpan};
     /// let mut scheduler = Scheduler::with_tz("UTC+3").unwrap();
     ///
     /// scheduler
-    ///     .every(1.month().on_the(28)) // example: Current month is December, this will next run on January 28th
+    ///     // example: It's December 29th, this will next run on January 28th
+    ///     .every(1.month().on_the(28).unwrap())
     ///     .perform(|| println!("hello!"));
+    ///
+    /// scheduler
+    ///     .every(3.month().on_the(31).unwrap().at("09:00").unwrap())
+    ///     .perform(|| println!("quarterly!"));
+    /// ```
+    pub fn on_the(mut self, month_day: u8) -> Result<Self, SpanError> {
+        if !(1..=31).contains(&month_day) {
+            return Err(SpanError::MonthDay(month_day));
+        }
+        self.month_day.replace(MonthDay::Day(month_day, ShortMonthPolicy::Clamp));
+        Ok(self)
+    }
+
+    /// Sets what happens in months that don't have the day given to [TimeSpan::on_the]
+    pub fn on_short_month(mut self, policy: ShortMonthPolicy) -> Self {
+        if let Some(MonthDay::Day(_, ref mut current)) = self.month_day {
+            *current = policy;
+        }
+        self
+    }
+
+    /// Pins a `.month()` or `.year()` span to the last day of the month, e.g. for month-end jobs
+    /// ```
+    /// use scheduler::{Scheduler, time::AsTimeSpan};
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// scheduler
+    ///     .every(1.month().last_day().at("23:00").unwrap())
+    ///     .perform(|| println!("billing!"));
     /// ```
-    pub fn on_the(mut self, month_day: u8) -> Self {
-        unimplemented!()
+    pub fn last_day(mut self) -> Self {
+        self.month_day.replace(MonthDay::Last);
+        self
+    }
+
+    /// Runs on the Nth business day of every `.month()` or `.year()` span, see [TimeSpan::with_holidays]
+    /// Fails for days that aren't 1 to 23, no month has more business days than that
+    /// ```
+    /// use scheduler::Scheduler;
+    /// use scheduler::time::{AsTimeSpan, HolidayCalendar};
+    /// let mut scheduler = Scheduler::new();
+    /// let holidays = HolidayCalendar::from_dates([(2024, 12, 25), (2025, 1, 1)]);
+    ///
+    /// scheduler
+    ///     .every(
+    ///         1.month()
+    ///             .on_business_day(3)
+    ///             .unwrap()
+    ///             .with_holidays(holidays)
+    ///             .at("07:00")
+    ///             .unwrap(),
+    ///     )
+    ///     .perform(|| println!("3rd business day of the month!"));
+    /// ```
+    pub fn on_business_day(mut self, nth: u8) -> Result<Self, SpanError> {
+        if !(1..=23).contains(&nth) {
+            return Err(SpanError::BusinessDay(nth));
+        }
+        self.month_day.replace(MonthDay::BusinessDay(nth));
+        Ok(self)
+    }
+
+    /// Runs a span of whole weeks on `day` of the week, e.g. `2.week().on(Monday)` for every other
+    /// Monday. Weeks are counted from the first week of 1970 unless the span is anchored, see
+    /// [TimeSpan::anchored_at]. Fails for spans that aren't whole weeks
+    pub fn on(mut self, day: WeekDay) -> Result<Self, SpanError> {
+        match self.step {
+            Step::Week { day: ref mut current, .. } => *current = day,
+            Step::Second if self.interval > 0 && self.interval % WEEK_IN_SECS == 0 => {
+                let period = (self.interval / WEEK_IN_SECS).min(u32::MAX as u64) as u32;
+                self.step = Step::Week { period, day, anchor: WEEK_ANCHOR };
+            }
+            _ => return Err(SpanError::NotWeeks),
+        }
+        Ok(self)
+    }
+
+    /// Counts the weeks of [TimeSpan::on] from the week of `date`, so that the span runs in that
+    /// week and every `period`-th week before and after it. Fails for dates that don't exist and
+    /// spans that don't run on a weekday yet
+    /// ```
+    /// use scheduler::{Scheduler, SchedulerError};
+    /// use scheduler::time::{AsTimeSpan, WeekDay::Monday};
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// scheduler
+    ///     .every(2.week().on(Monday)?.anchored_at((2024, 6, 12))?.at("10:00")?)
+    ///     .perform(|| println!("sprint planning!"));
+    /// # Ok::<(), SchedulerError>(())
+    /// ```
+    pub fn anchored_at(self, date: (u32, u8, u8)) -> Result<Self, SpanError> {
+        let (year, month, day) = date;
+        let date = Date::checked(year, month, day).ok_or(SpanError::Date(year, month, day))?;
+        let days = TSComponents::from(date).as_timestamp() / DAY_IN_SECS as i64;
+        self.anchored_at_day(days)
+    }
+
+    /// Same as [TimeSpan::anchored_at] with an ISO 8601 week, fails for weeks that `year` doesn't
+    /// have
+    pub fn anchored_at_week(self, year: u32, week: u8) -> Result<Self, SpanError> {
+        if !(1..=TSComponents::iso_weeks_in_year(year)).contains(&week) {
+            return Err(SpanError::Week(week));
+        }
+        self.anchored_at_day(TSComponents::iso_week_monday(year, week))
+    }
+
+    fn anchored_at_day(mut self, days: i64) -> Result<Self, SpanError> {
+        let Step::Week { ref mut anchor, .. } = self.step else {
+            return Err(SpanError::NotWeeks);
+        };
+        *anchor = days - TSComponents::iso_weekday_of(days) as i64 + 1;
+        Ok(self)
+    }
+
+    /// Runs each sunrise, sunset or twilight `window` earlier, e.g.
+    /// `Solar::new(52.37, 4.90)?.sunset().before(30.minute())?`. Other spans are left as they are,
+    /// see [crate::time::Solar]. Fails for windows that aren't plain durations, e.g. `1.month()`,
+    /// and when the event ends up a day or more away
+    pub fn before(self, window: TimeSpan) -> Result<Self, SpanError> {
+        let secs = window.duration_secs()?.min(DAY_IN_SECS) as i64;
+        self.offset_solar(-secs)
+    }
+
+    /// Runs each sunrise, sunset or twilight `window` later, see [TimeSpan::before]
+    pub fn after(self, window: TimeSpan) -> Result<Self, SpanError> {
+        let secs = window.duration_secs()?.min(DAY_IN_SECS) as i64;
+        self.offset_solar(secs)
+    }
+
+    fn offset_solar(mut self, secs: i64) -> Result<Self, SpanError> {
+        if let Step::Solar(ref mut solar) = self.step {
+            // runs are looked for within a day of each event
+            let offset = solar.offset + secs;
+            if offset.unsigned_abs() >= DAY_IN_SECS {
+                return Err(SpanError::Offset);
+            }
+            solar.offset = offset;
+        }
+        Ok(self)
+    }
+
+    /// Sets the holidays that business day spans skip on top of weekends. The calendar can be shared
+    /// between spans by passing an `Arc<HolidayCalendar>`
+    pub fn with_holidays<C: Into<Arc<HolidayCalendar>>>(mut self, calendar: C) -> Self {
+        self.calendar.replace(calendar.into());
+        self
+    }
+
+    fn is_business(&self, date: &Date) -> bool {
+        match self.calendar {
+            Some(ref calendar) => calendar.is_business(date),
+            None => !matches!(date.day_of_week(), 0 | 6),
+        }
+    }
+
+    /// Returns the wall-clock time of the `period`-th business day after `now`, at `start_at` or midnight
+    fn next_business_day(&self, now: &Timestamp, period: u32) -> Timestamp {
+        // a calendar where every day is a holiday would otherwise loop forever
+        const MAX_DAYS: u32 = 10 * 366;
+
+        let mut date = Date::from(&TSComponents::from(now.as_timestamp()));
+        let mut remaining = period.max(1);
+        for _ in 0..MAX_DAYS {
+            if self.is_business(&date) {
+                let mut candidate = Timestamp::new(TSComponents::from(date).as_timestamp());
+                if let Some(ref start_at) = self.start_at {
+                    candidate.set_time(start_at);
+                }
+                if candidate > *now {
+                    remaining -= 1;
+                    if remaining == 0 {
+                        return candidate;
+                    }
+                }
+            }
+            date = date.add_days(1);
+        }
+
+        // no business day for years, look again once they passed rather than run right away
+        Timestamp::new(TSComponents::from(date).as_timestamp())
+    }
+
+    /// Returns the wall-clock time of the first `month_day` after `now`, trying the current month first
+    /// and then stepping `period` months at a time
+    fn next_month_day(&self, now: &Timestamp, period: u32) -> Timestamp {
+        // a calendar where no month has enough business days would otherwise loop forever
+        const MAX_PERIODS: u32 = 10 * 12;
+
+        let period = period.max(1);
+        let components = TSComponents::from(now.as_timestamp());
+        let (mut year, mut month) = (components.year, components.month);
+        if let Some(MonthDay::Weekday { month: Some(fixed), .. }) = self.month_day {
+            month = fixed;
+        }
+
+        let mut month_day = self.month_day.expect("month_day is set for day of month schedules");
+        if let MonthDay::Day(day, ShortMonthPolicy::Skip) = month_day {
+            // e.g. the 31st every 12 months starting in April would never run
+            let possible = (0..12)
+                .map(|step| (month as u32 - 1 + step * period) % 12 + 1)
+                .any(|month| TSComponents::days_in_month(2000, month as u8) >= day as u64);
+            if !possible {
+                month_day = MonthDay::Day(day, ShortMonthPolicy::Clamp);
+            }
+        }
+
+        for _ in 0..MAX_PERIODS {
+            let days = TSComponents::days_in_month(year, month) as u8;
+            let day = match month_day {
+                MonthDay::Last => Some(days),
+                MonthDay::BusinessDay(nth) => (1..=days)
+                    .filter(|day| self.is_business(&Date::new(year, month, *day)))
+                    .nth(nth as usize - 1),
+                MonthDay::Weekday { day, nth, .. } => {
+                    let first_dow = Date::new(year, month, 1).day_of_week();
+                    let first = 1 + (day as u8 + WEEK_DAYS - first_dow) % WEEK_DAYS;
+                    let last = first + (days - first) / WEEK_DAYS * WEEK_DAYS;
+                    match nth {
+                        Some(nth) => Some(first + (nth - 1) * WEEK_DAYS).filter(|day| *day <= days),
+                        None => Some(last),
+                    }
+                }
+                MonthDay::Day(day, _) if day <= days => Some(day),
+                MonthDay::Day(day, ShortMonthPolicy::Roll) => Some(day),
+                MonthDay::Day(_, ShortMonthPolicy::Skip) => None,
+                MonthDay::Day(_, ShortMonthPolicy::Clamp) => Some(days),
+            };
+
+            if let Some(day) = day {
+                // rolled days overflow into the next month through the timestamp arithmetic
+                let first = TSComponents::new(year, month, 1).as_timestamp();
+                let mut candidate = Timestamp::new(first + (day as i64 - 1) * DAY_IN_SECS as i64);
+                if let Some(ref start_at) = self.start_at {
+                    candidate.set_time(start_at);
+                }
+                if candidate > *now {
+                    return candidate;
+                }
+            }
+
+            let next = Date::new(year, month, 1).add_months(period);
+            (year, month) = (next.year, next.month);
+        }
+
+        // look again once the periods have passed, see [TimeSpan::next_business_day]
+        Timestamp::new(TSComponents::new(year, month, 1).as_timestamp())
     }
 }
 
+impl Trigger {
+    fn new(ts: TimeSpan) -> Self {
+        Self { ts, next: None }
+    }
+}
+
+/// Describes the span in English, e.g. "every Monday at 00:00", "every 2 days and 6 hours" or
+/// "every 5 months on the 28th". Exclusions and holiday calendars are left out
+impl Display for TimeSpan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.step {
+            Step::Union(ref triggers) => {
+                let members = triggers.iter().map(|trigger| trigger.ts.to_string());
+                return write!(f, "{}", members.collect::<Vec<_>>().join(" and "));
+            }
+            Step::Solar(ref solar) => {
+                let offset = describe_secs(solar.offset.unsigned_abs(), false);
+                if solar.offset < 0 {
+                    write!(f, "{offset} before ")?;
+                } else if solar.offset > 0 {
+                    write!(f, "{offset} after ")?;
+                }
+                return write!(f, "every {} at {}", solar.event, solar.solar);
+            }
+            Step::Cron(ref cron) => write!(f, "cron {cron}")?,
+            Step::Weekday(day) => write!(f, "every {day}")?,
+            Step::Second => write!(f, "every {}", describe_secs(self.interval, true))?,
+            Step::BusinessDay(period) => {
+                write!(f, "every {}", count(period, "business day", true))?
+            }
+            Step::Week { period, day, .. } => {
+                write!(f, "every {} on {day}", count(period, "week", true))?
+            }
+            Step::Month(period) => write!(f, "every {}", count(period as u32, "month", true))?,
+            Step::Year(period) => write!(f, "every {}", count(period, "year", true))?,
+        }
+
+        match self.month_day {
+            Some(MonthDay::Day(day, policy)) => {
+                write!(f, " on the {}", ordinal(day))?;
+                match policy {
+                    ShortMonthPolicy::Clamp => {}
+                    ShortMonthPolicy::Skip => write!(f, ", skipping short months")?,
+                    ShortMonthPolicy::Roll => write!(f, ", rolling over short months")?,
+                }
+            }
+            Some(MonthDay::Last) => write!(f, " on the last day")?,
+            Some(MonthDay::BusinessDay(nth)) => write!(f, " on the {} business day", ordinal(nth))?,
+            Some(MonthDay::Weekday { day, nth, month }) => {
+                match nth {
+                    Some(nth) => write!(f, " on the {} {day}", ordinal(nth))?,
+                    None => write!(f, " on the last {day}")?,
+                }
+                if let Some(month) = month {
+                    write!(f, " of {}", MONTH_NAMES[month as usize - 1])?;
+                }
+            }
+            None => {}
+        }
+        if let Some(ref start_at) = self.start_at {
+            write!(f, " at {start_at}")?;
+        }
+        if let Some(start_in) = self.start_in {
+            write!(f, " starting in {}", describe_secs(start_in, false))?;
+        }
+        if let Step::Week { anchor, .. } = self.step {
+            if anchor != WEEK_ANCHOR {
+                let monday = Timestamp::new(anchor * DAY_IN_SECS as i64);
+                write!(f, " from the week of {}", monday.format("%F"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// e.g. "2 days, 6 hours and 20 seconds". A single unit of 1 is only the unit when `bare` is set,
+/// to read as "every hour"
+fn describe_secs(secs: u64, bare: bool) -> String {
+    let units = [
+        (WEEK_IN_SECS, "week"),
+        (DAY_IN_SECS, "day"),
+        (HOUR_IN_SECS, "hour"),
+        (MINUTE_IN_SECS, "minute"),
+        (1, "second"),
+    ];
+    let mut remaining = secs;
+    let mut parts = Vec::new();
+    for (unit_secs, unit) in units {
+        if remaining >= unit_secs {
+            parts.push((remaining / unit_secs, unit));
+            remaining %= unit_secs;
+        }
+    }
+
+    match parts[..] {
+        [] => count(0u64, "second", false),
+        [(n, unit)] => count(n, unit, bare),
+        [ref init @ .., (n, unit)] => {
+            let init = init.iter().map(|(n, unit)| count(*n, unit, false));
+            format!("{} and {}", init.collect::<Vec<_>>().join(", "), count(n, unit, false))
+        }
+    }
+}
+
+fn count<N: Into<u64>>(n: N, unit: &str, bare: bool) -> String {
+    match n.into() {
+        1 if bare => unit.to_string(),
+        1 => format!("1 {unit}"),
+        n => format!("{n} {unit}s"),
+    }
+}
+
+/// e.g. 1st, 2nd, 3rd, 11th, 22nd
+fn ordinal(n: u8) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+/// Adds plain durations together, e.g. `2.hour() + 30.minute()`. Adding anything else, like a
+/// `WeekDay` or a `.month()` span, is the same as [TimeSpan::and]
 impl Add for TimeSpan {
     type Output = TimeSpan;
     fn add(mut self, rhs: Self) -> Self::Output {
+        if !self.is_duration() || !rhs.is_duration() {
+            return self.and(rhs);
+        }
         self.interval += rhs.interval;
         self
     }
@@ -124,6 +770,9 @@ where
 
     fn week(&self) -> TimeSpan;
 
+    /// Every N business days, skipping weekends and the holidays of [TimeSpan::with_holidays]
+    fn business_day(&self) -> TimeSpan;
+
     fn month(&self) -> TimeSpan;
 
     fn year(&self) -> TimeSpan;
@@ -131,68 +780,308 @@ where
 
 impl AsTimeSpan for u64 {
     fn second(&self) -> TimeSpan {
-        TimeSpan {
-            interval: *self,
-            start_at: None,
-            start_in: None,
-            step: Step::Second,
-        }
+        TimeSpan::new(*self, Step::Second)
     }
 
     fn minute(&self) -> TimeSpan {
-        TimeSpan {
-            interval: *self * MINUTE_IN_SECS,
-            start_at: None,
-            start_in: None,
-            step: Step::Second,
-        }
+        TimeSpan::new(*self * MINUTE_IN_SECS, Step::Second)
     }
 
     fn hour(&self) -> TimeSpan {
-        TimeSpan {
-            interval: *self * HOUR_IN_SECS,
-            start_at: None,
-            start_in: None,
-            step: Step::Second,
-        }
+        TimeSpan::new(*self * HOUR_IN_SECS, Step::Second)
     }
 
     fn day(&self) -> TimeSpan {
-        TimeSpan {
-            interval: *self * DAY_IN_SECS,
-            start_at: None,
-            start_in: None,
-            step: Step::Second,
-        }
+        TimeSpan::new(*self * DAY_IN_SECS, Step::Second)
     }
 
     fn week(&self) -> TimeSpan {
-        TimeSpan {
-            interval: *self * WEEK_IN_SECS,
-            start_at: None,
-            start_in: None,
-            step: Step::Second,
-        }
+        TimeSpan::new(*self * WEEK_IN_SECS, Step::Second)
+    }
+
+    fn business_day(&self) -> TimeSpan {
+        TimeSpan::new(0, Step::BusinessDay(*self as u32))
     }
 
     //TODO: .month and .year need major refactor im not sure about their behaviour
     fn month(&self) -> TimeSpan {
         // let interval = Timestamp::now().get_month_interval(*self as u8);
-        TimeSpan {
-            interval: 0,
-            start_at: None,
-            start_in: None,
-            step: Step::Month(*self as u8),
-        }
+        TimeSpan::new(0, Step::Month(*self as u8))
     }
 
     fn year(&self) -> TimeSpan {
         // let interval = Timestamp::now().get_year_interval(*self as u32);
-        TimeSpan {
-            interval: 0,
-            start_at: None,
-            start_in: None,
-            step: Step::Year(*self as u32),
+        TimeSpan::new(0, Step::Year(*self as u32))
+    }
+}
+
+impl Display for SpanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MonthDay(day) => write!(f, "day {day} of the month out of range"),
+            Self::BusinessDay(nth) => {
+                write!(f, "business day {nth} out of range, a month has at most 23")
+            }
+            Self::Nth(nth) => write!(f, "occurrence {nth} of the weekday out of range, at most 5"),
+            Self::Month(month) => write!(f, "month {month} out of range"),
+            Self::Date(year, month, day) => write!(f, "{year:04}-{month:02}-{day:02} isn't a date"),
+            Self::NotADuration => write!(f, "expected a plain duration such as '30 minutes'"),
+            Self::Offset => write!(f, "an event can't be moved by a day or more"),
+            Self::NotWeeks => write!(f, "only spans of whole weeks run on a weekday"),
+            Self::Week(week) => write!(f, "week {week} out of range"),
+            Self::DateRange(from, to) => write!(
+                f,
+                "{:04}-{:02}-{:02} is after {:04}-{:02}-{:02}",
+                from.0, from.1, from.2, to.0, to.1, to.2
+            ),
         }
     }
 }
+
+impl Error for SpanError {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::time::{
+        AsTimeSpan, DAY_IN_SECS, HolidayCalendar, HOUR_IN_SECS, ShortMonthPolicy, SpanError, Step,
+        TimeError, TimeSpan, TimeZone,
+    };
+    use crate::time::WeekDay::Monday;
+    use crate::time::timestamp::Timestamp;
+    use crate::time::ts_components::TSComponents;
+
+    fn date(year: u32, month: u8, day: u8) -> Timestamp {
+        Timestamp::new(TSComponents::new(year, month, day).as_timestamp())
+    }
+
+    fn next(ts: &TimeSpan, now: Timestamp, period: u32) -> (u32, u8, u8) {
+        let next = TSComponents::from(ts.next_month_day(&now, period).as_timestamp());
+        (next.year, next.month, next.day)
+    }
+
+    #[test]
+    fn test_on_the() {
+        let ts = 1.month().on_the(28).unwrap();
+        assert_eq!(next(&ts, date(2024, 12, 5), 1), (2024, 12, 28));
+        assert_eq!(next(&ts, date(2024, 12, 29), 1), (2025, 1, 28));
+        assert_eq!(next(&ts, date(2024, 12, 28), 1), (2025, 1, 28));
+
+        let ts = 3.month().on_the(31).unwrap().at("09:00").unwrap();
+        let next_run = ts.next_month_day(&date(2024, 1, 31), 3);
+        let mut expected = TSComponents::new(2024, 1, 31);
+        expected.hour = 9;
+        assert_eq!(next_run.as_timestamp(), expected.as_timestamp());
+        // February only has 29 days in 2024
+        assert_eq!(next(&ts, date(2024, 2, 1), 3), (2024, 2, 29));
+
+        assert_eq!(1.month().on_the(0).err(), Some(SpanError::MonthDay(0)));
+        assert_eq!(1.month().on_the(32).err(), Some(SpanError::MonthDay(32)));
+    }
+
+    #[test]
+    fn test_short_months() {
+        let clamp = 1.month().on_the(31).unwrap();
+        let skip = 1.month().on_the(31).unwrap().on_short_month(ShortMonthPolicy::Skip);
+        let roll = 1.month().on_the(31).unwrap().on_short_month(ShortMonthPolicy::Roll);
+        let feb = date(2023, 2, 1);
+
+        assert_eq!(next(&clamp, date(2023, 2, 1), 1), (2023, 2, 28));
+        assert_eq!(next(&clamp, date(2024, 2, 1), 1), (2024, 2, 29));
+        assert_eq!(next(&skip, date(2023, 2, 1), 1), (2023, 3, 31));
+        assert_eq!(next(&skip, date(2023, 4, 1), 1), (2023, 5, 31));
+        assert_eq!(next(&roll, feb, 1), (2023, 3, 3));
+        assert_eq!(next(&roll, date(2023, 4, 1), 1), (2023, 5, 1));
+
+        // there's never a 31st in April, so skipping gives up and clamps
+        assert_eq!(next(&skip, date(2023, 4, 1), 12), (2023, 4, 30));
+    }
+
+    #[test]
+    fn test_last_day() {
+        let ts = 1.month().last_day();
+        assert_eq!(next(&ts, date(2023, 2, 1), 1), (2023, 2, 28));
+        assert_eq!(next(&ts, date(2024, 2, 29), 1), (2024, 3, 31));
+        assert_eq!(next(&ts, date(2024, 12, 31), 1), (2025, 1, 31));
+        assert_eq!(next(&1.year().last_day(), date(2024, 2, 29), 12), (2025, 2, 28));
+    }
+
+    #[test]
+    fn test_union() {
+        let tz = TimeZone::utc();
+        let now = Timestamp::new(1_718_355_600);
+
+        let mut ts = 1.hour().and(30.minute()).and(2.hour());
+        let Step::Union(ref triggers) = ts.step else {
+            panic!("expected a union");
+        };
+        assert_eq!(triggers.len(), 3);
+        assert_eq!(ts.next_run_on_after(&now, &tz).as_timestamp(), now.as_timestamp() + 1800);
+
+        // members that haven't fired yet keep their instant
+        let Step::Union(ref triggers) = ts.step else {
+            unreachable!()
+        };
+        for (trigger, secs) in triggers.iter().zip([3600, 1800, 7200]) {
+            assert_eq!(trigger.next.unwrap().as_timestamp(), now.as_timestamp() + secs);
+        }
+    }
+
+    #[test]
+    fn test_add() {
+        let ts = 2.day() + 6.hour() + 20.second();
+        assert_eq!(ts.interval, 2 * DAY_IN_SECS + 6 * HOUR_IN_SECS + 20);
+        assert!(matches!((1.day() + Monday.midnight()).step, Step::Union(_)));
+        assert!(matches!((1.month() + 1.day()).step, Step::Union(_)));
+    }
+
+    #[test]
+    fn test_business_days() {
+        let at = |year, month, day, hour| {
+            let mut components = TSComponents::new(year, month, day);
+            components.hour = hour;
+            Timestamp::new(components.as_timestamp())
+        };
+        let ymd = |ts: Timestamp| {
+            let c = TSComponents::from(ts.as_timestamp());
+            (c.year, c.month, c.day, c.hour)
+        };
+        let holidays = Arc::new(HolidayCalendar::from_dates([(2024, 6, 17), (2024, 7, 2)]));
+
+        // 2024-06-14 is a Friday
+        let daily = 1.business_day().at("07:00").unwrap();
+        assert_eq!(ymd(daily.next_business_day(&at(2024, 6, 14, 6), 1)), (2024, 6, 14, 7));
+        assert_eq!(ymd(daily.next_business_day(&at(2024, 6, 14, 8), 1)), (2024, 6, 17, 7));
+        assert_eq!(ymd(daily.next_business_day(&at(2024, 6, 13, 8), 2)), (2024, 6, 17, 7));
+
+        let daily = daily.with_holidays(holidays.clone());
+        assert_eq!(ymd(daily.next_business_day(&at(2024, 6, 14, 8), 1)), (2024, 6, 18, 7));
+
+        // June 1st 2024 is a Saturday, July 2nd is a holiday
+        let third = 1.month().on_business_day(3).unwrap().with_holidays(holidays);
+        assert_eq!(ymd(third.next_month_day(&at(2024, 6, 1, 0), 1)), (2024, 6, 5, 0));
+        assert_eq!(ymd(third.next_month_day(&at(2024, 6, 5, 0), 1)), (2024, 7, 4, 0));
+
+        // there are never 23 business days in February
+        let last = 1.month().on_business_day(23).unwrap();
+        assert_eq!(ymd(last.next_month_day(&at(2024, 2, 1, 0), 1)), (2024, 5, 31, 0));
+        assert_eq!(1.month().on_business_day(0).err(), Some(SpanError::BusinessDay(0)));
+        assert_eq!(1.month().on_business_day(24).err(), Some(SpanError::BusinessDay(24)));
+
+
+        // without any business day for years the next run is still ahead of now, once they passed
+        let holidays = (2024..2036).flat_map(|year| {
+            (1..=12).flat_map(move |month| {
+                let days = TSComponents::days_in_month(year, month) as u8;
+                (1..=days).map(move |day| (year, month, day))
+            })
+        });
+        let holidays = Arc::new(HolidayCalendar::from_dates(holidays));
+        let never = 1.business_day().with_holidays(holidays.clone());
+        assert_eq!(ymd(never.next_business_day(&at(2024, 6, 14, 8), 1)), (2034, 6, 22, 0));
+        let never = 1.month().on_business_day(3).unwrap().with_holidays(holidays);
+        assert_eq!(ymd(never.next_month_day(&at(2024, 6, 14, 8), 1)), (2034, 6, 1, 0));
+    }
+
+    #[test]
+    fn test_weeks() {
+        use crate::time::WeekDay::{Friday, Sunday};
+
+        let utc = TimeZone::utc();
+        let ymd = |ts: Timestamp| ts.format("%F %R");
+
+        // the sprint started on Wednesday 2024-06-12, planning is every other Monday
+        let ts = 2.week().on(Monday).unwrap().anchored_at((2024, 6, 12)).unwrap();
+        let mut ts = ts.at("10:00").unwrap();
+        assert_eq!(ymd(ts.next_after(&date(2024, 6, 10), &utc)), "2024-06-10 10:00");
+        assert_eq!(ymd(ts.next_after(&date(2024, 6, 11), &utc)), "2024-06-24 10:00");
+        assert_eq!(ymd(ts.next_after(&date(2024, 6, 18), &utc)), "2024-06-24 10:00");
+        assert_eq!(ymd(ts.next_after(&date(2024, 5, 26), &utc)), "2024-05-27 10:00");
+
+        // the anchor works both ways and across the end of the year
+        let mut ts = 3.week().on(Sunday).unwrap().anchored_at_week(2025, 1).unwrap();
+        assert_eq!(ymd(ts.next_after(&date(2024, 12, 10), &utc)), "2024-12-15 00:00");
+        assert_eq!(ymd(ts.next_after(&date(2024, 12, 30), &utc)), "2025-01-05 00:00");
+        assert_eq!(ymd(ts.next_after(&date(2025, 1, 6), &utc)), "2025-01-26 00:00");
+
+        // without an anchor weeks are counted from 1970
+        let mut ts = 2.week().on(Friday).unwrap();
+        let next = ts.next_after(&date(2024, 6, 10), &utc);
+        assert_eq!(ymd(next), "2024-06-21 00:00");
+        assert_eq!(ymd(ts.next_after(&next, &utc)), "2024-07-05 00:00");
+
+        // in the wall-clock time of the timezone
+        let athens = TimeZone::parse("UTC+3").unwrap();
+        let mut ts = 1.week().on(Monday).unwrap().at("00:30").unwrap();
+        assert_eq!(ymd(ts.next_after(&date(2024, 6, 9), &athens)), "2024-06-09 21:30");
+
+        // only whole weeks run on a weekday
+        assert_eq!(10.day().on(Monday).err(), Some(SpanError::NotWeeks));
+        assert_eq!(1.month().on(Monday).err(), Some(SpanError::NotWeeks));
+        assert_eq!(14.day().anchored_at((2024, 6, 12)).err(), Some(SpanError::NotWeeks));
+        let weeks = || 2.week().on(Monday).unwrap();
+        assert_eq!(weeks().anchored_at((2024, 2, 30)).err(), Some(SpanError::Date(2024, 2, 30)));
+        assert_eq!(weeks().anchored_at((2024, 13, 1)).err(), Some(SpanError::Date(2024, 13, 1)));
+        // 2026 has 53 ISO weeks, 2025 has 52
+        assert!(weeks().anchored_at_week(2026, 53).is_ok());
+        assert_eq!(weeks().anchored_at_week(2025, 53).err(), Some(SpanError::Week(53)));
+        assert_eq!(weeks().anchored_at_week(2025, 0).err(), Some(SpanError::Week(0)));
+        assert!(matches!(14.day().on(Monday).unwrap().step, Step::Week { period: 2, .. }));
+    }
+
+    #[test]
+    fn test_display() {
+        use crate::time::WeekDay::{Friday, Thursday, Tuesday};
+
+        let cases = [
+            (Monday.midnight(), "every Monday at 00:00"),
+            (2.day() + 6.hour(), "every 2 days and 6 hours"),
+            (1.hour() + 30.minute() + 5.second(), "every 1 hour, 30 minutes and 5 seconds"),
+            (1.hour(), "every hour"),
+            (14.day(), "every 2 weeks"),
+            (3.day().at("03:45").unwrap(), "every 3 days at 03:45"),
+            (5.month().on_the(28).unwrap(), "every 5 months on the 28th"),
+            (
+                1.month().on_the(31).unwrap().on_short_month(ShortMonthPolicy::Skip),
+                "every month on the 31st, skipping short months",
+            ),
+            (1.month().last_day().at("23:00").unwrap(), "every month on the last day at 23:00"),
+            (1.month().on_business_day(3).unwrap(), "every month on the 3rd business day"),
+            (2.business_day(), "every 2 business days"),
+            (
+                Tuesday.nth_of_month(2).unwrap().at("18:00").unwrap(),
+                "every month on the 2nd Tuesday at 18:00",
+            ),
+            (Friday.last_of_month(), "every month on the last Friday"),
+            (Thursday.nth_in(11, 4).unwrap(), "every year on the 4th Thursday of November"),
+            (1.year(), "every year"),
+            (2.week().on(Monday).unwrap().at("10:00").unwrap(), "every 2 weeks on Monday at 10:00"),
+            (
+                1.week().on(Friday).unwrap().anchored_at((2024, 6, 12)).unwrap(),
+                "every week on Friday from the week of 2024-06-10",
+            ),
+            (
+                Monday.midnight().and(Friday.evening()),
+                "every Monday at 00:00 and every Friday at 18:00",
+            ),
+        ];
+        for (ts, expected) in cases {
+            assert_eq!(ts.to_string(), expected);
+        }
+
+        let cron = crate::time::cron("0 9 * * 1-5").unwrap();
+        assert_eq!(cron.to_string(), "cron 0 9 * * 1-5");
+    }
+
+    #[test]
+    fn test_am_pm() {
+        let at = |time_str| 1.day().at(time_str).unwrap();
+        assert_eq!(at("10:00").am().unwrap().to_string(), "every day at 10:00");
+        assert_eq!(at("12:30").am().unwrap().to_string(), "every day at 00:30");
+        assert_eq!(at("3:45").pm().unwrap().to_string(), "every day at 15:45");
+        assert_eq!(at("12:00").pm().unwrap().to_string(), "every day at 12:00");
+        assert_eq!(at("15:00").am().err(), Some(TimeError::Hour(15)));
+        assert_eq!(at("00:00").pm().err(), Some(TimeError::Hour(0)));
+    }
+}
@@ -1,233 +1,263 @@
 use std::cmp::Ordering;
-use std::ops::{Add, AddAssign, Div, Sub};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::time::{Date, DAY_IN_SECS, Time};
+use crate::time::{Date, DAY_IN_SECS, Time, WeekDay};
 use crate::time::ts_components::TSComponents;
 
+const NANOS_IN_SEC: u32 = 1_000_000_000;
+
+/// An instant in UTC with nanosecond precision, from 0000-01-01T00:00:00 through 9999-12-31T23:59:59.
+/// Instants before 1970 have negative timestamps
+#[derive(Clone, Copy)]
 pub struct Timestamp {
-    inner: u64,
+    /// Whole seconds since the unix epoch, rounded towards the past
+    inner: i64,
+    /// Nanoseconds after `inner`, always less than a second
+    nanos: u32,
     components: TSComponents,
 }
 
 impl Timestamp {
+    /// 0000-01-01T00:00:00
+    pub const MIN_SECS: i64 = -62_167_219_200;
+    /// 9999-12-31T23:59:59
+    pub const MAX_SECS: i64 = 253_402_300_799;
+
     pub fn now() -> Self {
-        let now_ts = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let components = TSComponents::from(now_ts);
-
-        Self {
-            inner: now_ts,
-            components,
-        }
+        let now = SystemTime::now();
+        let since_epoch = match now.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => since_epoch.as_nanos() as i128,
+            Err(err) => -(err.duration().as_nanos() as i128),
+        };
+
+        Self::from_nanos(since_epoch).expect("the system clock is out of range")
     }
 
-    pub fn new(ts: u64) -> Self {
-        let components = TSComponents::from(ts);
-        Self {
-            inner: ts,
-            components,
-        }
+    /// Panics if `secs` is out of range, see [Timestamp::from_secs]
+    pub fn new(secs: i64) -> Self {
+        Self::from_secs(secs).expect("timestamp out of range")
+    }
+
+    /// Returns `None` if `secs` is outside of [Timestamp::MIN_SECS] and [Timestamp::MAX_SECS]
+    pub fn from_secs(secs: i64) -> Option<Self> {
+        Self::from_parts(secs, 0)
+    }
+
+    /// Nanoseconds since the unix epoch, negative before 1970
+    pub fn from_nanos(nanos: i128) -> Option<Self> {
+        let secs = i64::try_from(nanos.div_euclid(NANOS_IN_SEC as i128)).ok()?;
+        Self::from_parts(secs, nanos.rem_euclid(NANOS_IN_SEC as i128) as u32)
     }
 
-    pub fn as_timestamp(&self) -> u64 {
+    fn from_parts(secs: i64, nanos: u32) -> Option<Self> {
+        (Self::MIN_SECS..=Self::MAX_SECS).contains(&secs).then(|| Self {
+            inner: secs,
+            nanos,
+            components: TSComponents::from(secs),
+        })
+    }
+
+    /// Whole seconds since the unix epoch, rounded towards the past so 1969-12-31T23:59:59.5 is -1
+    pub fn as_timestamp(&self) -> i64 {
         self.inner
     }
 
+    /// The fraction of the second in nanoseconds
+    pub fn subsec_nanos(&self) -> u32 {
+        self.nanos
+    }
+
+    pub fn as_nanos(&self) -> i128 {
+        self.inner as i128 * NANOS_IN_SEC as i128 + self.nanos as i128
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        Self::from_nanos(self.as_nanos().checked_add(duration.as_nanos() as i128)?)
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        Self::from_nanos(self.as_nanos().checked_sub(duration.as_nanos() as i128)?)
+    }
+
+    /// Adds (or subtracts when negative) whole seconds
+    pub fn checked_add_secs(&self, secs: i64) -> Option<Self> {
+        Self::from_parts(self.inner.checked_add(secs)?, self.nanos)
+    }
+
+    /// Same as [Timestamp::checked_add_secs] but stops at the first or last supported second
+    pub(crate) fn saturating_add_secs(&self, secs: i64) -> Self {
+        let secs = self.inner.saturating_add(secs).clamp(Self::MIN_SECS, Self::MAX_SECS);
+        Self::from_parts(secs, self.nanos).expect("seconds are clamped to the supported range")
+    }
+
+    /// The time elapsed from `earlier` until `self`, `None` if `earlier` is later than `self`
+    pub fn duration_since(&self, earlier: &Self) -> Option<Duration> {
+        let nanos = u128::try_from(self.as_nanos() - earlier.as_nanos()).ok()?;
+        let secs = u64::try_from(nanos / NANOS_IN_SEC as u128).ok()?;
+        Some(Duration::new(secs, (nanos % NANOS_IN_SEC as u128) as u32))
+    }
+
     pub fn day_of_week(&self) -> u8 {
-        (((self.inner / DAY_IN_SECS) + 4) % 7) as u8
+        (self.inner.div_euclid(DAY_IN_SECS as i64) + 4).rem_euclid(7) as u8
+    }
+
+    /// From Monday (1) to Sunday (7) in UTC, unlike [Timestamp::day_of_week] which starts on Sunday
+    pub fn iso_weekday(&self) -> u8 {
+        TSComponents::iso_weekday_of(self.inner.div_euclid(DAY_IN_SECS as i64))
     }
 
+    /// The ISO 8601 week-numbering year and week (1 to 53) of the UTC date, e.g. 2024-12-30 is in
+    /// week 1 of 2025
+    pub fn iso_week(&self) -> (u32, u8) {
+        self.components.iso_week()
+    }
+
+    /// Midnight UTC of `day` in an ISO week, `None` if `week` is 0 or past the last week of `year`
+    /// ```
+    /// use scheduler::time::WeekDay::Monday;
+    /// use scheduler::time::timestamp::Timestamp;
+    ///
+    /// let monday = Timestamp::from_iso_week(2025, 1, Monday).unwrap();
+    /// assert_eq!(monday.to_string(), "2024-12-30T00:00:00Z");
+    /// assert_eq!(monday.iso_week(), (2025, 1));
+    /// assert!(Timestamp::from_iso_week(2024, 53, Monday).is_none());
+    /// ```
+    pub fn from_iso_week(year: u32, week: u8, day: WeekDay) -> Option<Self> {
+        if week == 0 || week > TSComponents::iso_weeks_in_year(year) {
+            return None;
+        }
+        let days = TSComponents::iso_week_monday(year, week) + day.iso_index() as i64 - 1;
+        Self::from_secs(days * DAY_IN_SECS as i64)
+    }
+
+    /// Seconds from `self` until the same date and time `period` years later, see [Date::add_years]
     pub(crate) fn get_year_interval(&self, period: u32) -> u64 {
-        let date = Date::today().incr_year(period);
-        let mut future = Self::now();
+        let date = Date::from(&self.components).add_years(period);
+        let mut future = *self;
         future.set_date(&date);
 
-        (Duration::from_secs(future.get_components().as_timestamp())
-            - Duration::from_secs(self.as_timestamp()))
-            .as_secs()
+        (future.inner - self.inner) as u64
     }
 
+    /// Seconds from `self` until the same date and time `period` months later, see [Date::add_months]
     pub(crate) fn get_month_interval(&self, period: u8) -> u64 {
-        let date = Date::today().incr_month(period);
-        let mut future = Self::now();
+        let date = Date::from(&self.components).add_months(period as u32);
+        let mut future = *self;
         future.set_date(&date);
 
-        (Duration::from_secs(future.get_components().as_timestamp())
-            - Duration::from_secs(self.as_timestamp()))
-            .as_secs()
+        (future.inner - self.inner) as u64
     }
 
-    // TODO: refactor, this might not wrap around, should test
+    /// Also clears the fraction of the second
     pub(crate) fn set_time(&mut self, time: &Time) {
         self.components.hour = time.hours;
         self.components.minute = time.minutes;
-        if let Some(seconds) = time.seconds {
-            self.components.second = seconds;
-        }
+        self.components.second = time.seconds.unwrap_or(0);
 
         self.inner = self.components.as_timestamp();
+        self.nanos = 0;
     }
 
-    // TODO: refactor, this might not wrap around, should test
+    /// Keeps the time of day, `date` is expected to be valid (see [Date::add_months])
     pub(crate) fn set_date(&mut self, date: &Date) {
         self.components.year = date.year;
         self.components.month = date.month;
-        self.components.second = date.day;
+        self.components.day = date.day;
 
         self.inner = self.components.as_timestamp();
     }
-
-    pub(crate) fn get_components(&mut self) -> &TSComponents {
-        &self.components
-    }
 }
 
 impl Add<u64> for Timestamp {
     type Output = Timestamp;
-    fn add(mut self, rhs: u64) -> Self::Output {
-        self.inner += rhs;
-        self.components = TSComponents::from(self.inner);
-        self
+    fn add(self, rhs: u64) -> Self::Output {
+        self + Duration::from_secs(rhs)
     }
 }
 
 impl Add<Duration> for Timestamp {
     type Output = Self;
-    fn add(mut self, rhs: Duration) -> Self::Output {
-        self.inner += rhs.as_secs();
-        self.components = TSComponents::from(self.inner);
-        self
+    fn add(self, rhs: Duration) -> Self::Output {
+        self.checked_add(rhs).expect("overflow when adding duration to timestamp")
     }
 }
 
 impl AddAssign<Duration> for Timestamp {
     fn add_assign(&mut self, rhs: Duration) {
-        self.inner += rhs.as_secs();
-        self.components = TSComponents::from(self.inner);
+        *self = *self + rhs;
     }
 }
 
-
-impl PartialEq<Self> for Timestamp {
-    fn eq(&self, other: &Self) -> bool {
-        self.inner == other.inner
+impl Sub<Duration> for Timestamp {
+    type Output = Self;
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self.checked_sub(rhs).expect("overflow when subtracting duration from timestamp")
     }
 }
 
-impl PartialOrd<Self> for Timestamp {
-    fn ge(&self, other: &Self) -> bool {
-        self.inner >= other.inner
-    }
-
-    fn le(&self, other: &Self) -> bool {
-        self.inner <= other.inner
-    }
-
-    fn gt(&self, other: &Self) -> bool {
-        self.inner > other.inner
+impl SubAssign<Duration> for Timestamp {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
     }
+}
 
-    fn lt(&self, other: &Self) -> bool {
-        self.inner < other.inner
+impl PartialEq<Self> for Timestamp {
+    fn eq(&self, other: &Self) -> bool {
+        (self.inner, self.nanos) == (other.inner, other.nanos)
     }
+}
 
+impl PartialOrd<Self> for Timestamp {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self == other {
-            return Some(Ordering::Equal);
-        }
-        if self.inner < other.inner {
-            return Some(Ordering::Less);
-        }
-        if self.inner > other.inner {
-            return Some(Ordering::Greater);
-        }
-
-        None
+        Some(self.cmp(other))
     }
 }
 
 impl Eq for Timestamp {}
 
 impl Ord for Timestamp {
-    fn clamp(self, min: Self, max: Self) -> Self
-    where
-        Self: Sized,
-        Self: PartialOrd,
-    {
-        if self < min {
-            return min;
-        }
-
-        if self > max {
-            return max;
-        }
-
-        self
-    }
-
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
-    }
-
-    fn max(self, other: Self) -> Self
-    where
-        Self: Sized,
-    {
-        match self.cmp(&other) {
-            Ordering::Equal | Ordering::Less => other,
-            _ => self
-        }
-    }
-
-    fn min(self, other: Self) -> Self
-    where
-        Self: Sized,
-    {
-        match self.cmp(&other) {
-            Ordering::Equal | Ordering::Greater => other,
-            _ => self
-        }
+        (self.inner, self.nanos).cmp(&(other.inner, other.nanos))
     }
 }
 
-
 #[cfg(test)]
 pub mod tests {
     use std::time::Duration;
 
-    use crate::time::{DAY_IN_SECS, WeekDay, YEAR_IN_SECS};
+    use crate::time::{Clock, DAY_IN_SECS, MockClock, WeekDay, YEAR_IN_SECS};
     use crate::time::timestamp::Timestamp;
+    use crate::time::ts_components::TSComponents;
 
-    // Bad test, need to specify the days manually each time
     #[test]
     fn test_day_of_week() {
+        // 2024-06-14T09:00:00Z is a Friday
+        let clock = MockClock::new(Timestamp::new(1_718_355_600));
         let days = vec![
-            (Timestamp::now(), WeekDay::Friday),
+            (clock.now(), WeekDay::Friday),
             (
-                Timestamp::now() + Duration::from_secs(DAY_IN_SECS * 6),
+                clock.now() + Duration::from_secs(DAY_IN_SECS * 6),
                 WeekDay::Thursday,
             ),
             (
-                Timestamp::now() + Duration::from_secs(DAY_IN_SECS * 3),
+                clock.now() + Duration::from_secs(DAY_IN_SECS * 3),
                 WeekDay::Monday,
             ),
             (
-                Timestamp::now() + Duration::from_secs(DAY_IN_SECS * 4),
+                clock.now() + Duration::from_secs(DAY_IN_SECS * 4),
                 WeekDay::Tuesday,
             ),
             (
-                Timestamp::now() + Duration::from_secs(DAY_IN_SECS * 7),
+                clock.now() + Duration::from_secs(DAY_IN_SECS * 7),
                 WeekDay::Friday,
             ),
             (
-                Timestamp::now() + Duration::from_secs(DAY_IN_SECS * 13),
+                clock.now() + Duration::from_secs(DAY_IN_SECS * 13),
                 WeekDay::Thursday,
             ),
             (
-                Timestamp::now() + Duration::from_secs(YEAR_IN_SECS),
+                clock.now() + Duration::from_secs(YEAR_IN_SECS),
                 WeekDay::Saturday,
             ),
         ];
@@ -236,4 +266,50 @@ pub mod tests {
             assert_eq!(ts.day_of_week(), day as u8)
         }
     }
+
+    #[test]
+    fn test_month_and_year_intervals() {
+        let mut components = TSComponents::new(2024, 1, 31);
+        components.hour = 10;
+        components.minute = 30;
+        let ts = Timestamp::new(components.as_timestamp());
+        let after = |secs: u64| {
+            let c = TSComponents::from(ts.as_timestamp() + secs as i64);
+            (c.year, c.month, c.day, c.hour, c.minute)
+        };
+
+        assert_eq!(after(ts.get_month_interval(1)), (2024, 2, 29, 10, 30));
+        assert_eq!(after(ts.get_month_interval(2)), (2024, 3, 31, 10, 30));
+        assert_eq!(after(ts.get_month_interval(11)), (2024, 12, 31, 10, 30));
+        assert_eq!(after(ts.get_month_interval(13)), (2025, 2, 28, 10, 30));
+        assert_eq!(after(ts.get_year_interval(1)), (2025, 1, 31, 10, 30));
+
+        let leap_day = Timestamp::new(TSComponents::new(2024, 2, 29).as_timestamp());
+        let c = TSComponents::from((leap_day + leap_day.get_year_interval(1)).as_timestamp());
+        assert_eq!((c.year, c.month, c.day), (2025, 2, 28));
+    }
+
+    #[test]
+    fn test_signed_nanos() {
+        let before_epoch = Timestamp::from_nanos(-1_500_000_000).unwrap();
+        assert_eq!(before_epoch.as_timestamp(), -2);
+        assert_eq!(before_epoch.subsec_nanos(), 500_000_000);
+        assert_eq!(before_epoch.day_of_week(), WeekDay::Wednesday as u8);
+
+        let epoch = Timestamp::new(0);
+        assert!(before_epoch < epoch);
+        assert_eq!(epoch.duration_since(&before_epoch), Some(Duration::from_millis(1500)));
+        assert_eq!(before_epoch.duration_since(&epoch), None);
+
+        let later = before_epoch + Duration::from_millis(1750);
+        assert_eq!((later.as_timestamp(), later.subsec_nanos()), (0, 250_000_000));
+        assert!(later - Duration::from_millis(1750) == before_epoch);
+
+        let max = Timestamp::new(Timestamp::MAX_SECS);
+        assert!(max.checked_add(Duration::from_secs(1)).is_none());
+        assert!(Timestamp::new(Timestamp::MIN_SECS).checked_sub(Duration::from_nanos(1)).is_none());
+        assert!(Timestamp::from_secs(Timestamp::MAX_SECS + 1).is_none());
+        let year_before = max.checked_add_secs(-(YEAR_IN_SECS as i64)).unwrap();
+        assert_eq!(year_before.components.year, 9998);
+    }
 }
@@ -0,0 +1,660 @@
+// Timezones are either a fixed offset from UTC ("UTC", "UTC+3", "GMT-05:30") or a region from the IANA
+// database ("Europe/Athens"), read from the TZif files (RFC 8536) of the system zoneinfo directory.
+// The directory can be overridden with the `TZDIR` environment variable.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::time::{DAY_IN_SECS, HOUR_IN_SECS, MINUTE_IN_SECS};
+use crate::time::timestamp::Timestamp;
+use crate::time::ts_components::TSComponents;
+
+const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
+/// Offsets further than this from UTC are rejected
+const MAX_OFFSET_SECS: i64 = 18 * HOUR_IN_SECS as i64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeZoneError {
+    /// A fixed offset like "UTC+3" that could not be parsed or is out of range
+    InvalidOffset(String),
+    /// No zoneinfo file exists for the given name
+    UnknownZone(String),
+    /// The zoneinfo file exists but is not a valid TZif file
+    InvalidData(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeZone {
+    name: String,
+    /// UTC instants at which the offset changes, along with the index of the new offset in `offsets`
+    transitions: Vec<(i64, usize)>,
+    /// Offsets in seconds east of UTC, the first one applies before the first transition
+    offsets: Vec<i32>,
+    /// Applies after the last transition, taken from the footer of TZif files
+    rule: Option<PosixRule>,
+}
+
+/// A POSIX TZ string such as `EET-2EEST,M3.5.0/3,M10.5.0/4`. Offsets are stored east of UTC
+#[derive(Debug, Clone, PartialEq)]
+struct PosixRule {
+    std_offset: i32,
+    dst: Option<DstRule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DstRule {
+    offset: i32,
+    start: RuleDate,
+    /// Local time in seconds after midnight at which DST starts
+    start_time: i32,
+    end: RuleDate,
+    /// Local time in seconds after midnight at which DST ends
+    end_time: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RuleDate {
+    /// `Jn`: day 1-365, February 29th is never counted
+    Julian(u16),
+    /// `n`: day 0-365, February 29th is counted in leap years
+    ZeroBased(u16),
+    /// `Mm.w.d`: day `d` (0 is Sunday) of week `w` (5 is the last) of month `m`
+    MonthWeekDay(u8, u8, u8),
+}
+
+impl Default for TimeZone {
+    fn default() -> Self {
+        Self::utc()
+    }
+}
+
+impl TimeZone {
+    pub fn utc() -> Self {
+        Self::fixed("UTC", 0)
+    }
+
+    fn fixed(name: &str, offset: i32) -> Self {
+        Self {
+            name: name.to_string(),
+            transitions: Vec::new(),
+            offsets: vec![offset],
+            rule: None,
+        }
+    }
+
+    /// Accepts fixed offsets ("UTC", "UTC+3", "GMT-05:30") and IANA names ("Europe/Athens")
+    pub fn parse(tz: &str) -> Result<Self, TimeZoneError> {
+        let tz = tz.trim();
+        match Self::parse_fixed(tz) {
+            Some(offset) => Ok(Self::fixed(tz, offset?)),
+            None => Self::load(tz),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the offset from UTC, in seconds, that is in effect at `ts`
+    pub fn offset_at(&self, ts: &Timestamp) -> i32 {
+        self.offset_at_secs(ts.as_timestamp())
+    }
+
+    /// Converts a UTC [Timestamp] to one whose components hold the wall-clock time of the zone
+    pub(crate) fn to_local(&self, ts: &Timestamp) -> Timestamp {
+        ts.saturating_add_secs(self.offset_at_secs(ts.as_timestamp()) as i64)
+    }
+
+    /// Converts a wall-clock [Timestamp] of the zone back to UTC.
+    ///
+    /// Local times that are repeated when the clocks go back resolve to their first occurrence and
+    /// local times that are skipped when the clocks go forward resolve to the instant of the transition
+    pub(crate) fn to_utc(&self, local_ts: &Timestamp) -> Timestamp {
+        let local = local_ts.as_timestamp();
+        let day = DAY_IN_SECS as i64;
+        let before = self.offset_at_secs(local - day) as i64;
+        let after = self.offset_at_secs(local + day) as i64;
+
+        let valid = [before, after]
+            .into_iter()
+            .filter(|offset| self.offset_at_secs(local - offset) as i64 == *offset)
+            .map(|offset| local - offset)
+            .min();
+        if let Some(utc) = valid {
+            return local_ts.saturating_add_secs(utc - local);
+        }
+
+        // the local time falls in a gap, find the first instant that has the new offset
+        let (mut lo, mut hi) = (local - after, local - before);
+        if lo > hi {
+            return local_ts.saturating_add_secs(hi - local);
+        }
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.offset_at_secs(mid) as i64 == after {
+                true => hi = mid,
+                false => lo = mid + 1,
+            }
+        }
+
+        local_ts.saturating_add_secs(hi - local)
+    }
+
+    fn offset_at_secs(&self, utc: i64) -> i32 {
+        let idx = self.transitions.partition_point(|(at, _)| *at <= utc);
+        match (idx, &self.rule) {
+            (idx, Some(rule)) if idx == self.transitions.len() => rule.offset_at(utc),
+            (0, _) => self.offsets[0],
+            (idx, _) => self.offsets[self.transitions[idx - 1].1],
+        }
+    }
+
+    /// Returns `None` if `tz` is not a fixed offset at all and `Some(Err)` if it is an invalid one
+    fn parse_fixed(tz: &str) -> Option<Result<i32, TimeZoneError>> {
+        let offset = ["UTC", "GMT"]
+            .iter()
+            .find_map(|prefix| tz.strip_prefix(prefix))?;
+        if offset.is_empty() {
+            return Some(Ok(0));
+        }
+
+        let invalid = || TimeZoneError::InvalidOffset(tz.to_string());
+        let (sign, offset) = match offset.as_bytes()[0] {
+            b'+' => (1, &offset[1..]),
+            b'-' => (-1, &offset[1..]),
+            // e.g. "UTCX", let the zoneinfo lookup deal with it
+            _ => return None,
+        };
+
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "0"),
+        };
+        let parsed = match (hours.parse::<i64>(), minutes.parse::<i64>()) {
+            (Ok(h), Ok(m)) if hours.len() <= 2 && (0..60).contains(&m) => {
+                sign * (h * HOUR_IN_SECS as i64 + m * MINUTE_IN_SECS as i64)
+            }
+            _ => return Some(Err(invalid())),
+        };
+
+        match parsed.abs() <= MAX_OFFSET_SECS {
+            true => Some(Ok(parsed as i32)),
+            false => Some(Err(invalid())),
+        }
+    }
+
+    fn load(name: &str) -> Result<Self, TimeZoneError> {
+        let valid_name = !name.is_empty()
+            && !name.starts_with('/')
+            && name.split('/').all(|part| part != ".." && part != ".")
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/_-+".contains(c));
+        if !valid_name {
+            return Err(TimeZoneError::UnknownZone(name.to_string()));
+        }
+
+        let dir = std::env::var_os("TZDIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(ZONEINFO_DIR));
+        let data = std::fs::read(dir.join(name))
+            .map_err(|_| TimeZoneError::UnknownZone(name.to_string()))?;
+
+        Self::from_tzif(name, &data)
+    }
+
+    fn from_tzif(name: &str, data: &[u8]) -> Result<Self, TimeZoneError> {
+        let invalid = |reason: &str| TimeZoneError::InvalidData(format!("{name}: {reason}"));
+
+        let mut reader = TzifReader { data, pos: 0 };
+        let header = reader.header().ok_or_else(|| invalid("invalid header"))?;
+
+        // version 2+ files repeat the data with 64-bit transition times after the version 1 block
+        let (header, time_size) = match header.version {
+            0 => (header, 4),
+            _ => {
+                reader
+                    .skip(header.block_len(4))
+                    .ok_or_else(|| invalid("truncated data"))?;
+                (reader.header().ok_or_else(|| invalid("invalid header"))?, 8)
+            }
+        };
+
+        // the counts come from the file, so they're checked against its length before allocating
+        if header.block_len(time_size) > reader.remaining() {
+            return Err(invalid("truncated data"));
+        }
+
+        let mut times = Vec::with_capacity(header.time_count);
+        for _ in 0..header.time_count {
+            let time = match time_size {
+                4 => reader.i32().map(i64::from),
+                _ => reader.i64(),
+            };
+            times.push(time.ok_or_else(|| invalid("truncated transitions"))?);
+        }
+
+        let mut transitions = Vec::with_capacity(header.time_count);
+        for time in times {
+            let idx = reader.u8().ok_or_else(|| invalid("truncated transitions"))? as usize;
+            if idx >= header.type_count {
+                return Err(invalid("transition to unknown local time type"));
+            }
+            transitions.push((time, idx));
+        }
+
+        let mut offsets = Vec::with_capacity(header.type_count);
+        for _ in 0..header.type_count {
+            let offset = reader.i32().ok_or_else(|| invalid("truncated local time types"))?;
+            reader.skip(2).ok_or_else(|| invalid("truncated local time types"))?;
+            offsets.push(offset);
+        }
+        if offsets.is_empty() {
+            return Err(invalid("no local time types"));
+        }
+
+        reader
+            .skip(header.block_len(time_size) - header.time_count * (time_size + 1) - header.type_count * 6)
+            .ok_or_else(|| invalid("truncated data"))?;
+
+        let rule = match header.version {
+            0 => None,
+            _ => {
+                let footer = reader.footer().ok_or_else(|| invalid("invalid footer"))?;
+                match footer.is_empty() {
+                    true => None,
+                    false => Some(PosixRule::parse(footer).ok_or_else(|| invalid("invalid TZ string"))?),
+                }
+            }
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            transitions,
+            offsets,
+            rule,
+        })
+    }
+}
+
+impl FromStr for TimeZone {
+    type Err = TimeZoneError;
+
+    fn from_str(tz: &str) -> Result<Self, Self::Err> {
+        Self::parse(tz)
+    }
+}
+
+struct TzifHeader {
+    version: u8,
+    utc_count: usize,
+    std_count: usize,
+    leap_count: usize,
+    time_count: usize,
+    type_count: usize,
+    char_count: usize,
+}
+
+impl TzifHeader {
+    /// Length of the data block that follows the header, given the size of a transition time
+    fn block_len(&self, time_size: usize) -> usize {
+        self.time_count * (time_size + 1)
+            + self.type_count * 6
+            + self.char_count
+            + self.leap_count * (time_size + 4)
+            + self.std_count
+            + self.utc_count
+    }
+}
+
+struct TzifReader<'d> {
+    data: &'d [u8],
+    pos: usize,
+}
+
+impl<'d> TzifReader<'d> {
+    fn take(&mut self, len: usize) -> Option<&'d [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.take(4)
+            .map(|bytes| i32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        self.take(8)
+            .map(|bytes| i64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn header(&mut self) -> Option<TzifHeader> {
+        if self.take(4)? != b"TZif" {
+            return None;
+        }
+        let version = match self.u8()? {
+            0 => 0,
+            v @ b'2'..=b'9' => v - b'0',
+            _ => return None,
+        };
+        self.skip(15)?;
+
+        let mut count = || self.i32().and_then(|c| usize::try_from(c).ok());
+        Some(TzifHeader {
+            version,
+            utc_count: count()?,
+            std_count: count()?,
+            leap_count: count()?,
+            time_count: count()?,
+            type_count: count()?,
+            char_count: count()?,
+        })
+    }
+
+    /// The footer is a POSIX TZ string enclosed in newlines
+    fn footer(&mut self) -> Option<&'d str> {
+        let rest = self.data.get(self.pos..)?;
+        let rest = rest.strip_prefix(b"\n")?;
+        let end = rest.iter().position(|b| *b == b'\n')?;
+        std::str::from_utf8(&rest[..end]).ok()
+    }
+}
+
+impl PosixRule {
+    fn parse(src: &str) -> Option<Self> {
+        let mut src = src.as_bytes();
+
+        Self::parse_name(&mut src)?;
+        let std_offset = -Self::parse_time(&mut src)?;
+        if src.is_empty() {
+            return Some(Self {
+                std_offset,
+                dst: None,
+            });
+        }
+
+        Self::parse_name(&mut src)?;
+        let offset = match src.first() {
+            Some(b',') => std_offset + HOUR_IN_SECS as i32,
+            _ => -Self::parse_time(&mut src)?,
+        };
+
+        let default_time = 2 * HOUR_IN_SECS as i32;
+        src = src.strip_prefix(b",")?;
+        let start = Self::parse_date(&mut src)?;
+        let start_time = match src.strip_prefix(b"/") {
+            Some(rest) => {
+                src = rest;
+                Self::parse_time(&mut src)?
+            }
+            None => default_time,
+        };
+        src = src.strip_prefix(b",")?;
+        let end = Self::parse_date(&mut src)?;
+        let end_time = match src.strip_prefix(b"/") {
+            Some(rest) => {
+                src = rest;
+                Self::parse_time(&mut src)?
+            }
+            None => default_time,
+        };
+
+        src.is_empty().then_some(Self {
+            std_offset,
+            dst: Some(DstRule {
+                offset,
+                start,
+                start_time,
+                end,
+                end_time,
+            }),
+        })
+    }
+
+    fn parse_name(src: &mut &[u8]) -> Option<()> {
+        let len = match src.first()? {
+            b'<' => src.iter().position(|b| *b == b'>')? + 1,
+            _ => src.iter().take_while(|b| b.is_ascii_alphabetic()).count(),
+        };
+        if len < 3 {
+            return None;
+        }
+        *src = &src[len..];
+        Some(())
+    }
+
+    /// Parses `[+-]hh[:mm[:ss]]` into seconds
+    fn parse_time(src: &mut &[u8]) -> Option<i32> {
+        let sign = match src.first()? {
+            b'-' => -1,
+            b'+' => 1,
+            _ => 0,
+        };
+        if sign != 0 {
+            *src = &src[1..];
+        }
+
+        let mut total = 0;
+        for (idx, unit) in [HOUR_IN_SECS, MINUTE_IN_SECS, 1].into_iter().enumerate() {
+            if idx > 0 {
+                match src.strip_prefix(b":") {
+                    Some(rest) => *src = rest,
+                    None => break,
+                }
+            }
+            total += Self::parse_number(src)? as i32 * unit as i32;
+        }
+
+        Some(if sign == -1 { -total } else { total })
+    }
+
+    fn parse_date(src: &mut &[u8]) -> Option<RuleDate> {
+        match src.first()? {
+            b'J' => {
+                *src = &src[1..];
+                Some(RuleDate::Julian(Self::parse_number(src)?))
+            }
+            b'M' => {
+                *src = &src[1..];
+                let month = Self::parse_number(src)? as u8;
+                *src = src.strip_prefix(b".")?;
+                let week = Self::parse_number(src)? as u8;
+                *src = src.strip_prefix(b".")?;
+                let day = Self::parse_number(src)? as u8;
+                ((1..=12).contains(&month) && (1..=5).contains(&week) && day <= 6)
+                    .then_some(RuleDate::MonthWeekDay(month, week, day))
+            }
+            _ => Some(RuleDate::ZeroBased(Self::parse_number(src)?)),
+        }
+    }
+
+    fn parse_number(src: &mut &[u8]) -> Option<u16> {
+        let len = src.iter().take_while(|b| b.is_ascii_digit()).count();
+        let number = std::str::from_utf8(&src[..len]).ok()?.parse().ok()?;
+        *src = &src[len..];
+        Some(number)
+    }
+
+    fn offset_at(&self, utc: i64) -> i32 {
+        let Some(ref dst) = self.dst else {
+            return self.std_offset;
+        };
+
+        let year = TSComponents::from(utc + self.std_offset as i64).year;
+        let start = dst.start.local_midnight(year) + (dst.start_time - self.std_offset) as i64;
+        let end = dst.end.local_midnight(year) + (dst.end_time - dst.offset) as i64;
+
+        let in_dst = match start < end {
+            true => start <= utc && utc < end,
+            // southern hemisphere, DST spans the new year
+            false => !(end <= utc && utc < start),
+        };
+
+        match in_dst {
+            true => dst.offset,
+            false => self.std_offset,
+        }
+    }
+}
+
+impl RuleDate {
+    /// Seconds since the epoch of midnight of this date in `year`, as if the local time were UTC
+    fn local_midnight(&self, year: u32) -> i64 {
+        let jan_first = TSComponents::new(year, 1, 1).as_timestamp();
+        let day = DAY_IN_SECS as i64;
+        match *self {
+            RuleDate::Julian(n) => {
+                let leap_day = TSComponents::days_in_month(year, 2) == 29 && n >= 60;
+                jan_first + (n as i64 - 1 + leap_day as i64) * day
+            }
+            RuleDate::ZeroBased(n) => jan_first + n as i64 * day,
+            RuleDate::MonthWeekDay(month, week, weekday) => {
+                let first = TSComponents::new(year, month, 1).as_timestamp();
+                let first_weekday = (first.div_euclid(day) + 4).rem_euclid(7) as u8;
+                let mut month_day = 1 + (weekday + 7 - first_weekday) % 7 + (week - 1) * 7;
+                while month_day as u64 > TSComponents::days_in_month(year, month) {
+                    month_day -= 7;
+                }
+                first + (month_day as i64 - 1) * day
+            }
+        }
+    }
+}
+
+impl Display for TimeZoneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidOffset(tz) => write!(f, "invalid UTC offset: {tz}"),
+            Self::UnknownZone(tz) => write!(f, "unknown timezone: {tz}"),
+            Self::InvalidData(reason) => write!(f, "invalid timezone data: {reason}"),
+        }
+    }
+}
+
+impl Error for TimeZoneError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::timestamp::Timestamp;
+    use crate::time::timezone::{PosixRule, TimeZone, TimeZoneError};
+    use crate::time::ts_components::TSComponents;
+
+    fn utc(year: u32, month: u8, day: u8, hour: u8, minute: u8) -> i64 {
+        let mut components = TSComponents::new(year, month, day);
+        components.hour = hour;
+        components.minute = minute;
+        components.as_timestamp()
+    }
+
+    fn athens() -> TimeZone {
+        TimeZone {
+            name: "EET-2EEST,M3.5.0/3,M10.5.0/4".to_string(),
+            transitions: Vec::new(),
+            offsets: vec![7200],
+            rule: PosixRule::parse("EET-2EEST,M3.5.0/3,M10.5.0/4"),
+        }
+    }
+
+    #[test]
+    fn test_fixed_offsets() {
+        let cases = [
+            ("UTC", Ok(0)),
+            ("GMT", Ok(0)),
+            ("UTC+3", Ok(3 * 3600)),
+            ("UTC-05:30", Ok(-(5 * 3600 + 30 * 60))),
+            ("GMT+0545", Ok(5 * 3600 + 45 * 60)),
+            ("UTC+19", Err(TimeZoneError::InvalidOffset("UTC+19".to_string()))),
+            ("UTC+3:75", Err(TimeZoneError::InvalidOffset("UTC+3:75".to_string()))),
+        ];
+
+        for (tz, expected) in cases {
+            let offset = TimeZone::parse(tz).map(|tz| tz.offset_at(&Timestamp::new(0)));
+            assert_eq!(offset, expected, "{tz}");
+        }
+    }
+
+    #[test]
+    fn test_posix_rule() {
+        let tz = athens();
+        // DST in 2024 runs from March 31st 01:00 UTC to October 27th 01:00 UTC
+        let cases = [
+            (utc(2024, 3, 31, 0, 59), 7200),
+            (utc(2024, 3, 31, 1, 0), 10800),
+            (utc(2024, 10, 27, 0, 59), 10800),
+            (utc(2024, 10, 27, 1, 0), 7200),
+            (utc(2024, 7, 1, 0, 0), 10800),
+            (utc(2024, 12, 1, 0, 0), 7200),
+        ];
+
+        for (ts, expected) in cases {
+            assert_eq!(tz.offset_at(&Timestamp::new(ts)), expected);
+        }
+
+        let south = PosixRule::parse("<-03>3<-02>,M9.1.6/24,M4.1.6/24").unwrap();
+        assert_eq!(south.offset_at(utc(2024, 1, 1, 0, 0) as i64), -7200);
+        assert_eq!(south.offset_at(utc(2024, 6, 1, 0, 0) as i64), -10800);
+    }
+
+    #[test]
+    fn test_local_conversions() {
+        let tz = athens();
+        let to_utc = |ts: i64| tz.to_utc(&Timestamp::new(ts)).as_timestamp();
+
+        // regular times round-trip
+        let noon = utc(2024, 7, 1, 12, 0);
+        assert_eq!(to_utc(tz.to_local(&Timestamp::new(noon)).as_timestamp()), noon);
+
+        // 03:30 does not exist on March 31st, it runs at 04:00 local (01:00 UTC) instead
+        assert_eq!(to_utc(utc(2024, 3, 31, 3, 30)), utc(2024, 3, 31, 1, 0));
+
+        // 03:30 happens twice on October 27th, only the first occurrence is used
+        assert_eq!(to_utc(utc(2024, 10, 27, 3, 30)), utc(2024, 10, 27, 0, 30));
+    }
+
+    #[test]
+    fn test_zoneinfo() {
+        // a copy of the system file, so the test doesn't depend on the zoneinfo of the host
+        let data = include_bytes!("../../tests/zoneinfo/Europe/Athens");
+        let tz = TimeZone::from_tzif("Europe/Athens", data).unwrap();
+        assert_eq!(tz.offset_at(&Timestamp::new(utc(2024, 7, 1, 0, 0))), 10800);
+        assert_eq!(tz.offset_at(&Timestamp::new(utc(2024, 12, 1, 0, 0))), 7200);
+        // far beyond the transitions of the file, covered by the footer
+        assert_eq!(tz.offset_at(&Timestamp::new(utc(2090, 7, 1, 0, 0))), 10800);
+
+        assert!(matches!(
+            TimeZone::parse("Europe/Nowhere"),
+            Err(TimeZoneError::UnknownZone(_))
+        ));
+        assert!(matches!(
+            TimeZone::parse("../etc/passwd"),
+            Err(TimeZoneError::UnknownZone(_))
+        ));
+
+        // a header that claims more transitions than the file holds
+        let mut corrupt = b"TZif\0".to_vec();
+        corrupt.extend([0; 15]);
+        for count in [0, 0, 0, i32::MAX, 1, 0] {
+            corrupt.extend(count.to_be_bytes());
+        }
+        assert_eq!(
+            TimeZone::from_tzif("Corrupt", &corrupt),
+            Err(TimeZoneError::InvalidData("Corrupt: truncated data".to_string()))
+        );
+    }
+}
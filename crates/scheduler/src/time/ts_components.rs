@@ -1,5 +1,11 @@
-use crate::time::{DAY_IN_SECS, DAYS_IN_LEAP_MONTH, DAYS_IN_MONTH, EPOCH_YEAR, HOUR_IN_SECS, LEAP_YEAR_IN_SECS, MINUTE_IN_SECS, YEAR_IN_SECS};
+use crate::time::{Date, DAY_IN_SECS, HOUR_IN_SECS, MINUTE_IN_SECS};
 
+/// Days in 400 years of the gregorian calendar, after which the leap years repeat
+const DAYS_IN_ERA: i64 = 146_097;
+/// Days from 0000-03-01 to 1970-01-01
+const UNIX_EPOCH_DAYS: i64 = 719_468;
+
+#[derive(Clone, Copy)]
 pub(crate) struct TSComponents {
     pub(crate) year: u32,
     pub(crate) month: u8,
@@ -10,66 +16,60 @@ pub(crate) struct TSComponents {
 }
 
 impl TSComponents {
-    pub fn as_timestamp(&self) -> u64 {
-        let mut timestamp = 0;
-        for year in EPOCH_YEAR..self.year {
-            timestamp += Self::is_leap(year).then_some(LEAP_YEAR_IN_SECS).unwrap_or(YEAR_IN_SECS);
-        }
-        for month in 1..self.month {
-            timestamp += DAY_IN_SECS * Self::days_in_month(self.year, month);
+    /// Components for midnight of the given date
+    pub(crate) fn new(year: u32, month: u8, day: u8) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
         }
-        timestamp += DAY_IN_SECS * (self.day as u64 - 1);
-        timestamp += HOUR_IN_SECS * self.hour as u64;
-        timestamp += MINUTE_IN_SECS * self.minute as u64;
-        timestamp += self.second as u64;
-
-        timestamp
     }
 
-    fn extract_year(ts: &mut u64) -> u32 {
-        let mut year = EPOCH_YEAR as u64;
-        let mut seconds_in_year: u64;
-
-        let cycles = *ts / (400 * YEAR_IN_SECS + 97 * DAY_IN_SECS); // number of complete 400 year cycles
-        year += cycles * 400;
-        *ts %= 400 * YEAR_IN_SECS + 97 * DAY_IN_SECS;
-
-
-        let centuries = *ts / (100 * YEAR_IN_SECS + 24 * DAY_IN_SECS); // number of complete 100 year cycles
-        year += centuries * 100;
-        *ts %= 100 * YEAR_IN_SECS + 24 * DAY_IN_SECS;
-
-        let quadrennial = *ts / (4 * YEAR_IN_SECS + DAY_IN_SECS); // number of complete 4 year cycles
-        year += quadrennial * 4;
-        *ts %= 4 * YEAR_IN_SECS + DAY_IN_SECS;
-
-        // add the remaining years
-        while {
-            seconds_in_year = Self::is_leap(year as u32).then_some(LEAP_YEAR_IN_SECS).unwrap_or(YEAR_IN_SECS);
-            *ts >= seconds_in_year
-        } {
-            *ts -= seconds_in_year;
-            year += 1;
-        }
+    /// Seconds since the unix epoch, negative before 1970
+    pub fn as_timestamp(&self) -> i64 {
+        Self::days_from_civil(self.year, self.month, self.day) * DAY_IN_SECS as i64
+            + (HOUR_IN_SECS * self.hour as u64
+                + MINUTE_IN_SECS * self.minute as u64
+                + self.second as u64) as i64
+    }
 
-        year as u32
+    /// Days since 1970-01-01 in the proleptic gregorian calendar, counted in 400 year eras of
+    /// 146097 days that start on March 1st so the leap day is the last day of the year
+    fn days_from_civil(year: u32, month: u8, day: u8) -> i64 {
+        let year = year as i64 - (month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let month_from_march = (month as i64 + 9) % 12;
+        let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * DAYS_IN_ERA + day_of_era - UNIX_EPOCH_DAYS
     }
 
-    fn extract_month_and_day(mut ts: u64, year: u32) -> (u8, u8) {
-        let days_in_month = Self::is_leap(year).then_some(DAYS_IN_LEAP_MONTH).unwrap_or(DAYS_IN_MONTH);
-        let mut month = 0;
-        while ts >= days_in_month[month] * DAY_IN_SECS {
-            ts -= days_in_month[month] * DAY_IN_SECS;
-            month += 1;
-        }
-        ((month + 1) as u8, (ts / DAY_IN_SECS + 1) as u8)
+    /// The inverse of [TSComponents::days_from_civil]
+    fn civil_from_days(days: i64) -> (u32, u8, u8) {
+        let days = days + UNIX_EPOCH_DAYS;
+        let era = days.div_euclid(DAYS_IN_ERA);
+        let day_of_era = days.rem_euclid(DAYS_IN_ERA);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+        let month = (month_from_march + 2) % 12 + 1;
+        let year = era * 400 + year_of_era + (month <= 2) as i64;
+
+        (year as u32, month as u8, day as u8)
     }
 
-    fn time_from_secs(ts: u64) -> (u8, u8, u8) {
+    fn time_from_secs(secs: u64) -> (u8, u8, u8) {
         (
-            ((ts % DAY_IN_SECS) / HOUR_IN_SECS) as u8,
-            ((ts % DAY_IN_SECS) % HOUR_IN_SECS / MINUTE_IN_SECS) as u8,
-            ((ts % DAY_IN_SECS) % HOUR_IN_SECS % MINUTE_IN_SECS) as u8,
+            (secs / HOUR_IN_SECS) as u8,
+            (secs % HOUR_IN_SECS / MINUTE_IN_SECS) as u8,
+            (secs % MINUTE_IN_SECS) as u8,
         )
     }
 
@@ -77,7 +77,34 @@ impl TSComponents {
         year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
     }
 
-    fn days_in_month(year: u32, month: u8) -> u64 {
+    /// The ISO 8601 week-numbering year and week (1 to 53) of the date. Weeks start on Monday and
+    /// the first week of a year is the one with its first Thursday, so the first and last days of
+    /// a year may belong to the weeks of its neighbours
+    pub(crate) fn iso_week(&self) -> (u32, u8) {
+        let days = Self::days_from_civil(self.year, self.month, self.day);
+        let thursday = days - Self::iso_weekday_of(days) as i64 + 4;
+        let (year, _, _) = Self::civil_from_days(thursday);
+        let week = (thursday - Self::days_from_civil(year, 1, 1)) / 7 + 1;
+        (year, week as u8)
+    }
+
+    /// 52 or 53, December 28th is always in the last week of its year
+    pub(crate) fn iso_weeks_in_year(year: u32) -> u8 {
+        Self::new(year, 12, 28).iso_week().1
+    }
+
+    /// The Monday of an ISO week in days since the unix epoch, January 4th is always in week 1
+    pub(crate) fn iso_week_monday(year: u32, week: u8) -> i64 {
+        let jan_fourth = Self::days_from_civil(year, 1, 4);
+        jan_fourth - Self::iso_weekday_of(jan_fourth) as i64 + 1 + (week as i64 - 1) * 7
+    }
+
+    /// From Monday (1) to Sunday (7) of the day that's `days` after the unix epoch, a Thursday
+    pub(crate) fn iso_weekday_of(days: i64) -> u8 {
+        (days + 3).rem_euclid(7) as u8 + 1
+    }
+
+    pub(crate) fn days_in_month(year: u32, month: u8) -> u64 {
         match month {
             1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
             4 | 6 | 9 | 11 => 30,
@@ -88,11 +115,18 @@ impl TSComponents {
 }
 
 
-impl From<u64> for TSComponents {
-    fn from(mut value: u64) -> Self {
-        let year = Self::extract_year(&mut value); // should consider the case the value is negative
-        let (month, day) = Self::extract_month_and_day(value, year);
-        let time = Self::time_from_secs(value);
+impl From<Date> for TSComponents {
+    fn from(date: Date) -> Self {
+        Self::new(date.year, date.month, date.day)
+    }
+}
+
+/// `value` is in seconds since the unix epoch and is expected to fall within the years 0 to 9999,
+/// see [crate::time::timestamp::Timestamp]
+impl From<i64> for TSComponents {
+    fn from(value: i64) -> Self {
+        let (year, month, day) = Self::civil_from_days(value.div_euclid(DAY_IN_SECS as i64));
+        let time = Self::time_from_secs(value.rem_euclid(DAY_IN_SECS as i64) as u64);
 
         TSComponents {
             day,
@@ -111,6 +145,7 @@ mod tests {
 
     use rand::Rng;
 
+    use crate::time::DAY_IN_SECS;
     use crate::time::ts_components::TSComponents;
 
     macro_rules! gen_timestamps {
@@ -136,11 +171,11 @@ mod tests {
         }
     }
 
-    fn gen_timestamps() -> Vec<(u64, (u32, u32, u32))> {
+    fn gen_timestamps() -> Vec<(i64, (u32, u32, u32))> {
         let mut rng = rand::thread_rng();
-        let mut timestamps: Vec<(u64, (u32, u32, u32))> = Vec::with_capacity(100);
+        let mut timestamps: Vec<(i64, (u32, u32, u32))> = Vec::with_capacity(100);
         for _ in 0..1000 {
-            let year: i32 = rng.gen_range(1970..2038);
+            let year: i32 = rng.gen_range(1..10000);
             let month: u32 = rng.gen_range(1..13);
             let day: u32 = rng.gen_range(1..29);
 
@@ -149,7 +184,7 @@ mod tests {
                 .expect("Failed to parse date");
             let timestamp = date.and_utc().timestamp();
 
-            timestamps.push((timestamp as u64, (year as u32, month, day)));
+            timestamps.push((timestamp, (year as u32, month, day)));
         }
 
         timestamps
@@ -168,7 +203,50 @@ mod tests {
             assert_eq!(ts.year, expected.0);
             assert_eq!(ts.month as u32, expected.1);
             assert_eq!(ts.day as u32, expected.2);
+            assert_eq!(ts.as_timestamp(), timestamp);
         }
         println!("Took: {}μs", start.elapsed().as_micros());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_negative_timestamp() {
+        let ts = TSComponents::from(-1);
+        assert_eq!((ts.year, ts.month, ts.day), (1969, 12, 31));
+        assert_eq!((ts.hour, ts.minute, ts.second), (23, 59, 59));
+        assert_eq!(ts.as_timestamp(), -1);
+
+        // the moon landing
+        let ts = TSComponents::from(-14_182_940);
+        assert_eq!((ts.year, ts.month, ts.day, ts.hour, ts.minute), (1969, 7, 20, 20, 17));
+        assert_eq!(TSComponents::new(1900, 3, 1).as_timestamp(), -2_203_891_200);
+        assert_eq!(TSComponents::new(0, 1, 1).as_timestamp(), -62_167_219_200);
+    }
+
+    #[test]
+    fn test_iso_week() {
+        let cases = [
+            ((2024, 6, 14), (2024, 24)),
+            ((2024, 1, 1), (2024, 1)),
+            ((2024, 12, 30), (2025, 1)),
+            ((2021, 1, 3), (2020, 53)),
+            ((2020, 12, 31), (2020, 53)),
+            ((2027, 1, 1), (2026, 53)),
+            ((2005, 1, 2), (2004, 53)),
+            ((2008, 12, 29), (2009, 1)),
+            ((1970, 1, 1), (1970, 1)),
+            ((1969, 12, 29), (1970, 1)),
+        ];
+        for ((year, month, day), expected) in cases {
+            assert_eq!(TSComponents::new(year, month, day).iso_week(), expected);
+        }
+
+        assert_eq!(TSComponents::iso_weeks_in_year(2020), 53);
+        assert_eq!(TSComponents::iso_weeks_in_year(2024), 52);
+        assert_eq!(TSComponents::iso_weeks_in_year(2026), 53);
+
+        let monday = TSComponents::iso_week_monday(2025, 1) * DAY_IN_SECS as i64;
+        assert_eq!(TSComponents::new(2024, 12, 30).as_timestamp(), monday);
+        let monday = TSComponents::iso_week_monday(2020, 53) * DAY_IN_SECS as i64;
+        assert_eq!(TSComponents::new(2020, 12, 28).as_timestamp(), monday);
+    }
+}
@@ -6,13 +6,15 @@
 // ...
 // day of week    0-6 (0 is Sunday, or use names)
 
-use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
 
-use crate::time::{DAY_IN_SECS, Step, Time, WEEK_DAYS};
+use crate::time::{DAY_IN_SECS, MonthDay, SpanError, Step, Time, TimeError, WEEK_DAYS};
+use crate::time::format::WEEKDAY_NAMES;
 use crate::time::timespan::TimeSpan;
-use crate::time::timestamp::Timestamp;
 
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum WeekDay {
     Sunday = 0,
     Monday = 1,
@@ -24,56 +26,158 @@ pub enum WeekDay {
 }
 
 impl WeekDay {
-    // TODO: these methods might have a timezone bug, check it out
+    /// Every day of the week in the order of their values, starting from Sunday
+    pub(crate) const ALL: [WeekDay; 7] = [
+        Self::Sunday,
+        Self::Monday,
+        Self::Tuesday,
+        Self::Wednesday,
+        Self::Thursday,
+        Self::Friday,
+        Self::Saturday,
+    ];
+
+    /// From Monday (1) to Sunday (7) as in ISO 8601
+    pub fn iso_index(self) -> u8 {
+        (self as u8 + 6) % WEEK_DAYS + 1
+    }
+
     pub fn midnight(self) -> TimeSpan {
-        let mut ts = self.at("00:00").unwrap(); // it's safe because we know that the time_str is parsable
-        let until = Self::days_until(self as u8);
-        ts.start_in.replace(until as u64 * DAY_IN_SECS);
-        ts
+        self.at("00:00").unwrap() // it's safe because we know that the time_str is parsable
     }
 
     pub fn morning(self) -> TimeSpan {
-        let mut ts = self.at("06:00").unwrap(); // it's safe because we know that the time_str is parsable
-        let until = Self::days_until(self as u8);
-        ts.start_in.replace(until as u64 * DAY_IN_SECS);
-        ts
+        self.at("06:00").unwrap() // it's safe because we know that the time_str is parsable
     }
 
     pub fn afternoon(self) -> TimeSpan {
-        let mut ts = self.at("12:00").unwrap(); // it's safe because we know that the time_str is parsable
-        let until = Self::days_until(self as u8);
-        ts.start_in.replace(until as u64 * DAY_IN_SECS);
-        ts
+        self.at("12:00").unwrap() // it's safe because we know that the time_str is parsable
     }
 
-    // TODO: theres a bug that if the day that the schedule is supposed to start is exactly one week until execution it wont take into account the time of the day
-    // we should consider that and if the hour is still after ts.now() then start it then
     pub fn evening(self) -> TimeSpan {
-        let mut ts = self.at("18:00").unwrap(); // it's safe because we know that the time_str is parsable
-        let until = Self::days_until(self as u8);
-        ts.start_in.replace(until as u64 * DAY_IN_SECS);
-        ts
+        self.at("18:00").unwrap() // it's safe because we know that the time_str is parsable
+    }
+
+    /// The time is the wall-clock time in the timezone of the [crate::Scheduler]. If it's already `self`
+    /// and the time hasn't passed yet, the schedule first runs today
+    pub fn at(self, time_str: &str) -> Result<TimeSpan, TimeError> {
+        let mut ts = TimeSpan::new(WEEK_DAYS as u64 * DAY_IN_SECS, Step::Weekday(self));
+        ts.start_at.replace(Time::from_time_str(time_str)?);
+        Ok(ts)
+    }
+
+    /// The first `self` of every month, e.g. `Monday.first_of_month().at("09:00")`
+    pub fn first_of_month(self) -> TimeSpan {
+        self.of_month(Some(1), None, Step::Month(1))
+    }
+
+    /// The last `self` of every month
+    pub fn last_of_month(self) -> TimeSpan {
+        self.of_month(None, None, Step::Month(1))
+    }
+
+    /// The Nth `self` of every month, fails unless `nth` is 1 to 5. Months without a 5th `self` are
+    /// skipped
+    /// ```
+    /// use scheduler::Scheduler;
+    /// use scheduler::time::WeekDay::Tuesday;
+    /// let mut scheduler = Scheduler::new();
+    ///
+    /// scheduler
+    ///     .every(Tuesday.nth_of_month(2).unwrap().at("18:00").unwrap())
+    ///     .perform(|| println!("patch tuesday!"));
+    /// ```
+    pub fn nth_of_month(self, nth: u8) -> Result<TimeSpan, SpanError> {
+        Ok(self.of_month(Some(Self::checked_nth(nth)?), None, Step::Month(1)))
+    }
+
+    /// The first `self` of `month` (1 is January) every year, fails unless `month` is 1 to 12
+    pub fn first_in(self, month: u8) -> Result<TimeSpan, SpanError> {
+        self.nth_in(month, 1)
+    }
+
+    /// The last `self` of `month` (1 is January) every year, e.g. `Monday.last_in(5)`
+    pub fn last_in(self, month: u8) -> Result<TimeSpan, SpanError> {
+        Ok(self.of_month(None, Some(Self::checked_month(month)?), Step::Year(1)))
+    }
+
+    /// The Nth `self` of `month` (1 is January) every year, e.g. `Thursday.nth_in(11, 4)`. Fails
+    /// unless `month` is 1 to 12 and `nth` is 1 to 5, the years without a 5th `self` in `month` are
+    /// skipped
+    pub fn nth_in(self, month: u8, nth: u8) -> Result<TimeSpan, SpanError> {
+        let (month, nth) = (Self::checked_month(month)?, Self::checked_nth(nth)?);
+        Ok(self.of_month(Some(nth), Some(month), Step::Year(1)))
     }
 
-    pub fn at(self, time_str: &str) -> Result<TimeSpan, ()> {
-        let time_obj = match Time::from_time_str(time_str) {
-            Ok(t) => t,
-            Err(msg) => return Err(msg),
-        };
-        Ok(TimeSpan {
-            interval: WEEK_DAYS as u64 * DAY_IN_SECS,
-            start_in: None,
-            start_at: Some(time_obj),
-            step: Step::Weekday,
-        })
-    }
-
-    fn days_until(day: u8) -> u8 {
-        let today = Timestamp::now().day_of_week();
-        match today.cmp(&day) {
-            Ordering::Less => day - today,
-            Ordering::Greater => 7 - today + day,
-            Ordering::Equal => 7,
+    fn checked_nth(nth: u8) -> Result<u8, SpanError> {
+        match nth {
+            1..=5 => Ok(nth),
+            _ => Err(SpanError::Nth(nth)),
         }
     }
+
+    fn checked_month(month: u8) -> Result<u8, SpanError> {
+        match month {
+            1..=12 => Ok(month),
+            _ => Err(SpanError::Month(month)),
+        }
+    }
+
+    fn of_month(self, nth: Option<u8>, month: Option<u8>, step: Step) -> TimeSpan {
+        let mut ts = TimeSpan::new(0, step);
+        ts.month_day.replace(MonthDay::Weekday { day: self, nth, month });
+        ts
+    }
+}
+
+impl Display for WeekDay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", WEEKDAY_NAMES[*self as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::{SpanError, TimeSpan, TimeZone};
+    use crate::time::timestamp::Timestamp;
+    use crate::time::ts_components::TSComponents;
+    use crate::time::WeekDay::{Friday, Monday, Sunday, Thursday, Tuesday};
+
+    fn next(ts: &mut TimeSpan, year: u32, month: u8, day: u8) -> (u32, u8, u8, u8) {
+        let after = Timestamp::new(TSComponents::new(year, month, day).as_timestamp());
+        let next = TSComponents::from(ts.next_after(&after, &TimeZone::utc()).as_timestamp());
+        (next.year, next.month, next.day, next.hour)
+    }
+
+    #[test]
+    fn test_nth_of_month() {
+        let mut patch_tuesday = Tuesday.nth_of_month(2).unwrap().at("18:00").unwrap();
+        assert_eq!(next(&mut patch_tuesday, 2024, 1, 1), (2024, 1, 9, 18));
+        assert_eq!(next(&mut patch_tuesday, 2024, 1, 10), (2024, 2, 13, 18));
+        assert_eq!(next(&mut patch_tuesday, 2024, 12, 11), (2025, 1, 14, 18));
+
+        assert_eq!(next(&mut Monday.first_of_month(), 2024, 7, 2), (2024, 8, 5, 0));
+        assert_eq!(next(&mut Friday.last_of_month(), 2024, 3, 1), (2024, 3, 29, 0));
+        assert_eq!(next(&mut Friday.last_of_month(), 2024, 3, 30), (2024, 4, 26, 0));
+        // neither January nor February 2024 have a 5th Sunday
+        assert_eq!(next(&mut Sunday.nth_of_month(5).unwrap(), 2024, 1, 1), (2024, 3, 31, 0));
+
+        assert_eq!(Tuesday.nth_of_month(0).err(), Some(SpanError::Nth(0)));
+        assert_eq!(Tuesday.nth_of_month(6).err(), Some(SpanError::Nth(6)));
+    }
+
+    #[test]
+    fn test_nth_in() {
+        let mut thanksgiving = Thursday.nth_in(11, 4).unwrap();
+        assert_eq!(next(&mut thanksgiving, 2024, 1, 1), (2024, 11, 28, 0));
+        assert_eq!(next(&mut thanksgiving, 2024, 11, 29), (2025, 11, 27, 0));
+
+        assert_eq!(next(&mut Monday.last_in(5).unwrap(), 2024, 6, 1), (2025, 5, 26, 0));
+        assert_eq!(next(&mut Monday.first_in(9).unwrap(), 2024, 8, 15), (2024, 9, 2, 0));
+
+        assert_eq!(Thursday.nth_in(13, 4).err(), Some(SpanError::Month(13)));
+        assert_eq!(Thursday.nth_in(11, 0).err(), Some(SpanError::Nth(0)));
+        assert_eq!(Monday.last_in(0).err(), Some(SpanError::Month(0)));
+        assert_eq!(Monday.first_in(13).err(), Some(SpanError::Month(13)));
+    }
 }
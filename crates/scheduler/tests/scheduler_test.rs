@@ -1,8 +1,8 @@
-use scheduler::{schedule_every, time::AsTimeSpan};
+use scheduler::{schedule_every, SchedulerError, time::AsTimeSpan};
 use scheduler::time::WeekDay::{Monday, Tuesday};
 
 #[test]
-fn test_adding_schedules() -> Result<(), ()> {
+fn test_adding_schedules() -> Result<(), SchedulerError> {
     let threads = std::thread::available_parallelism().unwrap();
 
     println!("Available threads: {}", threads);
@@ -15,7 +15,7 @@ fn test_adding_schedules() -> Result<(), ()> {
             println!("Every 2 hours!")
         },
 
-        2.day().and(6.hour()) => {
+        2.day() + 6.hour() => {
             println!("Every 2 days and 6 hours!")
         },
 
@@ -43,13 +43,16 @@ fn test_adding_schedules() -> Result<(), ()> {
             println!("Every 2 years!")
         }
     );
-    
-    for sc in scheduler {
-        
+
+    assert_eq!(scheduler.schedules().count(), 11);
+    for sc in scheduler.schedules() {
+        assert!(!sc.is_completed());
     }
-    
-    scheduler.every(1.hour());
+    // the time given to .at() is read as before noon
+    let described = scheduler.schedules().map(ToString::to_string).collect::<Vec<_>>();
+    assert!(described.contains(&"every 3 days at 03:45".to_string()));
 
-    assert_eq!(1, 1);
+    scheduler.every(1.hour());
+    assert_eq!(scheduler.schedules().count(), 12);
     Ok(())
 }